@@ -0,0 +1,355 @@
+//! Running the simulation from the command line, without a window.
+
+use std::{collections::BTreeSet, fs, io::Write, path::{Path, PathBuf}, str::FromStr, thread, time::Duration};
+use crate::world::{calendar::Calendar, defs::SimulationConfig, diagnostics::{Diagnostic, has_errors}, event::{History, HistoryEvent}, save::{WorldFile, SaveError}, stats::{StatisticsConfig, PROBES}, sim::{Simulation, SimulationError, SimulationFailure, Recovered, systems_check, validate_world}, time::{Age, format_duration}};
+
+const USAGE: &str = "\
+Usage: world-gen run <world file> [options]
+
+Loads a saved world, simulates it to completion, and saves the result.
+
+Options:
+  -o, --output <file>     Where to save the simulated world. Defaults to the world file's name ending in '.out.ron',
+                          so the world file itself is left as it was.
+      --history <file>    Also write the world's history to a text file.
+      --steps <count>     Change how many steps the simulation runs for in total.
+                          This is kept between the soft limits of 50 and 1000 years.
+      --length <time>     Change how much in-world time the simulation runs for in total, like '200 years'.
+                          This is kept between the soft limits of 50 and 1000 years.
+      --snapshot <file>   Save the world as it was before simulating, unlocked so it can be run again.
+      --statistics <file> Also write statistics collected while simulating to a CSV file.
+      --probes <names>    Which statistics to collect, separated by commas, or 'all'.
+                          Defaults to tick time, entities, people, and places.
+      --sample-every <n>  How many steps between statistics samples. Defaults to 1.
+  -q, --quiet             Don't print progress.
+  -h, --help              Show this message.";
+
+/// Options for a headless run, read from the command line.
+pub struct RunOptions {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub history: Option<PathBuf>,
+    pub steps: Option<u32>,
+    pub length: Option<Age>,
+    pub snapshot: Option<PathBuf>,
+    pub statistics: Option<PathBuf>,
+    pub statistics_config: StatisticsConfig,
+    pub quiet: bool,
+}
+
+/// Error type for headless runs.
+#[derive(Debug)]
+pub enum CliError {
+    /// The arguments couldn't be understood.
+    Usage(String),
+    /// The world couldn't be loaded or saved.
+    Save(SaveError),
+    /// The world has problems that stop it from being simulated.
+    InvalidWorld(Vec<Diagnostic>),
+    /// The simulation couldn't be started.
+    Simulation(SimulationError),
+    /// The simulation stopped because of an error.
+    Failed(SimulationFailure),
+    /// The history couldn't be written.
+    Io(std::io::Error),
+}
+
+impl From<SaveError> for CliError {
+    fn from(value: SaveError) -> Self {
+        Self::Save(value)
+    }
+}
+
+impl From<SimulationError> for CliError {
+    fn from(value: SimulationError) -> Self {
+        Self::Simulation(value)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Returns `true` if the arguments ask for a headless run or help with one, rather than the window.
+pub fn is_cli(args: &[String]) -> bool {
+    matches!(args.first().map(|arg| arg.as_str()), Some("run" | "help" | "-h" | "--help"))
+}
+
+/// Runs the command line interface with the arguments passed to the program, excluding the program name.
+pub fn run_cli(args: &[String]) -> Result<(), CliError> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("run") => match parse_run_options(&args[1..])? {
+            Some(options) => run(options),
+            None => {
+                println!("{}", USAGE);
+                Ok(())
+            },
+        },
+        Some("-h" | "--help" | "help") => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        Some(other) => Err(CliError::Usage(format!("unknown command '{}'", other))),
+        None => Err(CliError::Usage("no command given".to_string())),
+    }
+}
+
+/// Reads the options for a headless run. Returns `None` if help was asked for instead.
+fn parse_run_options(args: &[String]) -> Result<Option<RunOptions>, CliError> {
+    let mut input: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut history: Option<PathBuf> = None;
+    let mut steps: Option<u32> = None;
+    let mut length: Option<Age> = None;
+    let mut snapshot: Option<PathBuf> = None;
+    let mut statistics: Option<PathBuf> = None;
+    let mut statistics_config = StatisticsConfig::default();
+    let mut quiet = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(CliError::Usage(format!("'{}' needs a value", arg)));
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value()?.into()),
+            "--history" => history = Some(value()?.into()),
+            "--steps" => {
+                let value = value()?;
+                steps = Some(value.parse().map_err(|_| CliError::Usage(format!("'{}' is not a number of steps", value)))?);
+            },
+            "--length" => {
+                let value = value()?;
+                length = Some(Age::from_str(value.trim()).map_err(|_| CliError::Usage(format!("'{}' is not a length of time, like '200 years 6 months'", value)))?);
+            },
+            "--snapshot" => snapshot = Some(value()?.into()),
+            "--statistics" => statistics = Some(value()?.into()),
+            "--probes" => statistics_config.enabled = parse_probes(value()?)?,
+            "--sample-every" => {
+                let value = value()?;
+                statistics_config.interval = value.parse().ok().filter(|interval| *interval > 0)
+                    .ok_or(CliError::Usage(format!("'{}' is not a number of steps", value)))?;
+            },
+            "-q" | "--quiet" => quiet = true,
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') => return Err(CliError::Usage(format!("unknown option '{}'", arg))),
+            _ if input.is_none() => input = Some(arg.into()),
+            _ => return Err(CliError::Usage(format!("unexpected argument '{}'", arg))),
+        }
+    }
+
+    let Some(input) = input else { return Err(CliError::Usage("no world file given".to_string())); };
+    if steps.is_some() && length.is_some() { return Err(CliError::Usage("'--steps' and '--length' can't be used together".to_string())); }
+
+    Ok(Some(RunOptions {
+        output: output.unwrap_or_else(|| default_output(&input)),
+        input,
+        history,
+        steps,
+        length,
+        snapshot,
+        statistics,
+        statistics_config,
+        quiet,
+    }))
+}
+
+/// Returns where to save a simulated world if nowhere was given, next to the world file without replacing it.
+fn default_output(input: &Path) -> PathBuf {
+    let mut name = input.file_stem().unwrap_or_default().to_os_string();
+    name.push(".out.ron");
+    input.with_file_name(name)
+}
+
+/// Reads a list of probe names separated by commas, ignoring case.
+fn parse_probes(list: &str) -> Result<BTreeSet<&'static str>, CliError> {
+    if list.eq_ignore_ascii_case("all") { return Ok(PROBES.iter().map(|probe| probe.name).collect()); }
+
+    list.split(',').map(|name| {
+        let name = name.trim();
+        PROBES.iter().find(|probe| probe.name.eq_ignore_ascii_case(name)).map(|probe| probe.name).ok_or_else(|| {
+            let known: Vec<&str> = PROBES.iter().map(|probe| probe.name).collect();
+            CliError::Usage(format!("unknown statistic '{}', expected one of: {}", name, known.join(", ")))
+        })
+    }).collect()
+}
+
+/// Changes how long the world runs for from the options, if they ask for it.
+/// Returns an error if that leaves no steps to run, since the world has already run for at least as long.
+fn set_run_length(config: &mut SimulationConfig, options: &RunOptions) -> Result<(), CliError> {
+    if let Some(steps) = options.steps {
+        config.increments_for_completion = steps;
+        let length = config.run_length();
+        config.set_run_length(length);
+        if config.increments_for_completion != steps {
+            eprintln!("Running for {} steps instead, to stay within the soft limits", config.increments_for_completion);
+        }
+    }
+    if let Some(length) = options.length {
+        let used = config.set_run_length(length);
+        if used != length { eprintln!("Running for {} instead, to stay within the soft limits", used); }
+    }
+
+    match config.increments_completed < config.increments_for_completion {
+        true => Ok(()),
+        false => Err(CliError::Usage(format!(
+            "the world has already run {} of its {} steps, so there's nothing left to run. Use '--steps' or '--length' to run it for longer",
+            config.increments_completed, config.increments_for_completion,
+        ))),
+    }
+}
+
+/// Loads, simulates, and saves a world.
+pub fn run(options: RunOptions) -> Result<(), CliError> {
+    let mut simulation = Simulation::new(WorldFile::read(&options.input)?.restore()?);
+
+    {
+        let data = simulation.current_or_err()?;
+        set_run_length(&mut data.app.world.resource_mut::<SimulationConfig>(), &options)?;
+
+        // Warnings don't stop the run, but should still be seen
+        let diagnostics = validate_world(&mut data.app.world);
+        if has_errors(&diagnostics) { return Err(CliError::InvalidWorld(diagnostics)); }
+        for diagnostic in diagnostics.iter() { eprintln!("{}", diagnostic); }
+
+        systems_check(data);
+    }
+
+    simulation.configure_statistics(options.statistics_config.clone())?;
+
+    let (mut simulation, result) = simulation.try_execute();
+    result?;
+
+    if let (Some(path), Some(file)) = (&options.snapshot, simulation.pre_run_snapshot()) {
+        file.write(path)?;
+        if !options.quiet { eprintln!("Saved pre-run snapshot to {}", path.display()); }
+    }
+
+    // Report progress until the simulation is done
+    while !simulation.is_finished() {
+        // Reading the status keeps messages from the simulation from piling up, even when quiet
+        let status = simulation.status();
+        if let (false, Some(status)) = (options.quiet, status) {
+            let remaining = status.time_remaining().map(|remaining| format!(", about {} left", format_duration(remaining))).unwrap_or_default();
+            // Padded so a shorter line fully covers the one before
+            eprint!("\r{:<60}", format!("Step {} of {}{}", status.steps_complete, status.steps_total, remaining));
+            let _ = std::io::stderr().flush();
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+
+    let (mut simulation, result) = simulation.join();
+    if let Err(failure) = result {
+        // Don't lose the progress made before the failure
+        let kept = match failure.recovered {
+            Recovered::LastGoodState(tick) => Some(format!("from just before step {}", tick + 1)),
+            Recovered::Checkpoint(tick) => Some(format!("from the checkpoint at step {}", tick)),
+            Recovered::PreRunSnapshot | Recovered::Nothing => None,
+        };
+        if let Some(kept) = kept {
+            let mut path = options.output.clone().into_os_string();
+            path.push(".recovered");
+            WorldFile::capture(simulation.world()?)?.write(&path)?;
+            eprintln!("\nSaved the world {} to {}", kept, PathBuf::from(path).display());
+        }
+        return Err(CliError::Failed(failure));
+    }
+    if let (false, Some(condition)) = (options.quiet, simulation.stopped_by()) {
+        eprintln!("\r{:<60}", format!("Stopped early: {}", condition));
+    }
+    let world = simulation.world()?;
+
+    if !options.quiet {
+        let cfg = world.resource::<SimulationConfig>();
+        eprintln!("\r{:<60}", format!("Finished {} of {} steps", cfg.increments_completed, cfg.increments_for_completion));
+    }
+
+    WorldFile::capture(world)?.write(&options.output)?;
+    if !options.quiet { eprintln!("Saved world to {}", options.output.display()); }
+
+    if let Some(path) = &options.history {
+        let mut text = String::new();
+        let calendar = world.get_resource::<Calendar>().cloned().unwrap_or_default();
+        if let Some(history) = world.get_resource::<History>() {
+            // Chronological order, since backwards runs record the most recent events first
+            let mut events: Vec<&HistoryEvent> = history.iter().collect();
+            events.sort_by_key(|event| (event.date, event.tick));
+            for event in events {
+                text.push_str(&format!("{}\t{}\t{}\n", calendar.format_after(event.date), event.kind.name(), event.summary));
+            }
+        }
+        fs::write(path, text)?;
+        if !options.quiet { eprintln!("Saved history to {}", path.display()); }
+    }
+
+    if let Some(path) = &options.statistics {
+        let calendar = world.get_resource::<Calendar>().cloned().unwrap_or_default();
+        simulation.statistics().write_csv(path, &calendar)?;
+        if !options.quiet { eprintln!("Saved statistics to {}", path.display()); }
+    }
+
+    Ok(())
+}
+
+/// Prints an error from [run_cli] in a way people can read.
+pub fn report_error(error: &CliError) {
+    match error {
+        CliError::Usage(message) => eprintln!("error: {}\n\n{}", message, USAGE),
+        CliError::InvalidWorld(diagnostics) => {
+            eprintln!("error: the world can't be simulated until these problems are fixed");
+            for diagnostic in diagnostics { eprintln!("{}", diagnostic); }
+        },
+        other => eprintln!("error: {:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::world::{defs::SimulationConfig, testing::sample_world};
+    use super::{CliError, is_cli, parse_run_options, set_run_length};
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn only_commands_run_without_a_window() {
+        assert!(is_cli(&args(&["run", "world.ron"])));
+        assert!(is_cli(&args(&["--help"])));
+        assert!(!is_cli(&args(&[])));
+        assert!(!is_cli(&args(&["-psn_0_12345"])));
+    }
+
+    #[test]
+    fn output_defaults_beside_the_world_file() {
+        let options = parse_run_options(&args(&["worlds/world.ron"])).unwrap().unwrap();
+        assert_eq!(options.input, PathBuf::from("worlds/world.ron"));
+        assert_eq!(options.output, PathBuf::from("worlds/world.out.ron"));
+
+        let options = parse_run_options(&args(&["world.ron", "-o", "generated.ron"])).unwrap().unwrap();
+        assert_eq!(options.output, PathBuf::from("generated.ron"));
+    }
+
+    #[test]
+    fn help_is_not_an_error() {
+        assert!(parse_run_options(&args(&["world.ron", "-h"])).unwrap().is_none());
+    }
+
+    #[test]
+    fn run_must_leave_steps_to_run() {
+        let mut simulation = sample_world();
+        let mut config = simulation.world().unwrap().resource_mut::<SimulationConfig>().clone();
+        config.increments_completed = 1200;
+
+        let shorter = parse_run_options(&args(&["world.ron", "--length", "50 years"])).unwrap().unwrap();
+        assert!(matches!(set_run_length(&mut config, &shorter), Err(CliError::Usage(_))));
+
+        let unchanged = parse_run_options(&args(&["world.ron"])).unwrap().unwrap();
+        assert!(matches!(set_run_length(&mut config, &unchanged), Err(CliError::Usage(_))));
+
+        let longer = parse_run_options(&args(&["world.ron", "--length", "200 years"])).unwrap().unwrap();
+        assert!(set_run_length(&mut config, &longer).is_ok());
+        assert_eq!(config.increments_for_completion, 2400);
+    }
+}
@@ -0,0 +1,149 @@
+//! Switching between and comparing branches of the world.
+
+use eframe::egui;
+use crate::world::branch::BranchSummary;
+use super::{WorldGenApp, AppMemory, notifs::{Notification, NotificationType}};
+
+/// The branch to switch to or delete.
+pub(super) const BRANCH_TARGET_KEY: &str = "branch_target";
+/// The name of a branch that's about to be made.
+pub(super) const NEW_BRANCH_KEY: &str = "new_branch_name";
+
+/// A row of the branch comparison: what it's called, and how to read it from each branch.
+type ComparisonRow = (&'static str, fn(&BranchSummary) -> String);
+
+/// Deals with branch actions from the UI.
+pub(super) fn branch_fns(
+    app: &mut WorldGenApp,
+) {
+    // Keep the names available to the editor, which can't see the simulation itself
+    app.memory.branch_names = app.simulation.branch_names();
+    app.memory.current_branch = app.simulation.branch().to_string();
+
+    if app.memory.markers.remove("fork_branch") {
+        let name = app.memory.string_map.get(NEW_BRANCH_KEY).cloned().unwrap_or_default();
+        match app.simulation.fork_branch(name.trim()) {
+            Ok(()) => {
+                app.memory.string_map.remove(NEW_BRANCH_KEY);
+                app.memory.notifications.push(Notification::new(format!("Started branch '{}'.", name.trim()), 5.0, NotificationType::Info));
+            },
+            Err(error) => app.memory.notifications.push(Notification::new(format!("Couldn't start the branch: {:?}", error), 10.0, NotificationType::Error)),
+        }
+        refresh_comparison(app);
+    }
+
+    if app.memory.markers.remove("switch_branch") {
+        let name = app.memory.string_map.remove(BRANCH_TARGET_KEY).unwrap_or_default();
+        if let Err(error) = app.simulation.switch_branch(&name) {
+            app.memory.notifications.push(Notification::new(format!("Couldn't switch branch: {:?}", error), 10.0, NotificationType::Error));
+        }
+        app.memory.focus = None;
+        app.memory.family_tree = None;
+    }
+
+    if app.memory.markers.remove("delete_branch") {
+        let name = app.memory.string_map.remove(BRANCH_TARGET_KEY).unwrap_or_default();
+        if let Err(error) = app.simulation.delete_branch(&name) {
+            app.memory.notifications.push(Notification::new(format!("Couldn't delete branch: {:?}", error), 10.0, NotificationType::Error));
+        }
+        refresh_comparison(app);
+    }
+
+    if app.memory.markers.remove("compare_branches") {
+        refresh_comparison(app);
+    }
+}
+
+/// Recalculates the comparison, if it's being shown.
+fn refresh_comparison(app: &mut WorldGenApp) {
+    if app.memory.branch_comparison.is_none() && !app.memory.markers.contains("branch_comparison_open") { return; }
+    app.memory.markers.remove("branch_comparison_open");
+
+    match app.simulation.branch_summaries() {
+        Ok(summaries) => app.memory.branch_comparison = Some(summaries),
+        Err(error) => app.memory.notifications.push(Notification::new(format!("Couldn't compare branches: {:?}", error), 10.0, NotificationType::Error)),
+    }
+}
+
+/// Shows every branch side by side.
+pub(super) fn comparison_window(
+    ctx: &egui::Context,
+    memory: &mut AppMemory,
+    frozen: bool,
+) {
+    let Some(summaries) = &memory.branch_comparison else { return; };
+
+    let mut open = true;
+    let mut action: Option<(&str, String)> = None;
+
+    egui::Window::new("Compare branches")
+    .open(&mut open)
+    .default_size([500.0, 250.0])
+    .show(ctx, |ui| {
+        egui::ScrollArea::both()
+        .id_source("branch_comparison_scroll")
+        .show(ui, |ui| {
+            egui::Grid::new("branch_comparison_grid")
+            .striped(true)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("");
+                for summary in summaries.iter() {
+                    let text = egui::RichText::new(&summary.name).strong();
+                    if summary.name == memory.current_branch { ui.label(text.underline()); } else { ui.label(text); }
+                }
+                ui.end_row();
+
+                let rows: [ComparisonRow; 7] = [
+                    ("Time simulated", |s| format!("{}", s.time_elapsed)),
+                    ("Seed", |s| s.seed.to_string()),
+                    ("Population", |s| s.population.to_string()),
+                    ("Dead", |s| s.dead.to_string()),
+                    ("Births", |s| s.births.to_string()),
+                    ("Deaths", |s| s.deaths.to_string()),
+                    ("Events", |s| s.events.to_string()),
+                ];
+
+                for (label, value) in rows {
+                    ui.label(label);
+                    for summary in summaries.iter() {
+                        ui.label(value(summary));
+                    }
+                    ui.end_row();
+                }
+
+                // Actions for branches other than the current one
+                ui.label("");
+                for summary in summaries.iter() {
+                    if summary.name == memory.current_branch {
+                        ui.label("(current)");
+                        continue;
+                    }
+                    ui.add_enabled_ui(frozen, |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("Switch").clicked() { action = Some(("switch_branch", summary.name.clone())); }
+                            if ui.button("Delete").clicked() { action = Some(("delete_branch", summary.name.clone())); }
+                        });
+                    });
+                }
+                ui.end_row();
+            });
+        });
+
+        ui.separator();
+
+        ui.add_enabled_ui(frozen, |ui| {
+            if ui.button("Refresh").clicked() {
+                memory.markers.insert("compare_branches".to_string());
+            }
+        });
+    });
+
+    if let Some((marker, name)) = action {
+        memory.string_map.insert(BRANCH_TARGET_KEY.to_string(), name);
+        memory.markers.insert(marker.to_string());
+        memory.markers.insert("compare_branches".to_string());
+    }
+
+    if !open { memory.branch_comparison = None; }
+}
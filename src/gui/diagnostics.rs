@@ -0,0 +1,68 @@
+//! A window listing problems with the world.
+
+use eframe::{egui, epaint::Color32};
+use crate::world::diagnostics::{Severity, has_errors};
+use super::AppMemory;
+
+/// Shows the problems found the last time the world was checked, if there were any.
+pub(super) fn diagnostics_window(
+    ctx: &egui::Context,
+    memory: &mut AppMemory,
+) {
+    if memory.diagnostics.is_empty() { return; }
+
+    let mut open = true;
+    let blocking = has_errors(&memory.diagnostics);
+
+    egui::Window::new("Problems with the world")
+    .open(&mut open)
+    .default_size([450.0, 300.0])
+    .collapsible(false)
+    .show(ctx, |ui| {
+        if blocking {
+            ui.label("The simulation can't start until the errors are fixed.");
+        } else {
+            ui.label("The simulation can start, but these might not be intended.");
+        }
+
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+        .id_source("diagnostics_scroll")
+        .max_height(300.0)
+        .show(ui, |ui| {
+            for diagnostic in memory.diagnostics.iter() {
+                let (icon, color) = match diagnostic.severity {
+                    Severity::Error => ("⛔", Color32::LIGHT_RED),
+                    Severity::Warning => ("⚠", Color32::YELLOW),
+                };
+
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(egui::RichText::new(icon).color(color)).on_hover_text(format!("{:?}", diagnostic.severity));
+                    if let Some(entity) = diagnostic.entity {
+                        ui.label(egui::RichText::new(format!("{:?}", entity)).monospace());
+                    }
+                    ui.label(&diagnostic.message);
+                });
+            }
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if !blocking && ui.button("Start anyway").clicked() {
+                memory.markers.insert("try_execute_simulation".to_string());
+                memory.markers.insert("diagnostics_acknowledged".to_string());
+                memory.markers.insert("close_diagnostics".to_string());
+            }
+
+            if ui.button("Close").clicked() {
+                memory.markers.insert("close_diagnostics".to_string());
+            }
+        });
+    });
+
+    if !open || memory.markers.remove("close_diagnostics") {
+        memory.diagnostics.clear();
+    }
+}
@@ -0,0 +1,98 @@
+use eframe::egui;
+use crate::world::calendar::{Calendar, CalendarDate, Month};
+
+/// Settings for the world's calendar. These mostly change how dates are shown, but the months also decide when each season is.
+pub(super) fn calendar_settings(
+    ui: &mut egui::Ui,
+    calendar: &mut Calendar,
+) {
+    egui::Grid::new("calendar_settings_grid")
+    .spacing([10.0, 3.0])
+    .striped(true)
+    .show(ui, |ui| {
+        ui.label("Era");
+        ui.add(egui::TextEdit::singleline(&mut calendar.epoch).hint_text("e.g. AR").desired_width(80.0)).on_hover_text(
+            "Written after the year in dates, like 'AR' in '14 Frostmere, 1203 AR'. This can be left empty."
+        );
+        ui.end_row();
+
+        ui.label("Starts on");
+        let mut start = calendar.start;
+        date_picker(ui, "calendar_start", &mut start, &calendar.months).response.on_hover_text(
+            "The date in the world when the simulation begins. When generating backwards, this is the earliest date history goes back to."
+        );
+        calendar.start = start;
+        ui.end_row();
+
+        ui.label("Year length");
+        ui.label(format!("{} days", calendar.year_length()));
+        ui.end_row();
+    });
+
+    ui.add_space(3.0);
+
+    // Months, which can be renamed, resized, reordered, and removed
+    let mut remove: Option<usize> = None;
+    let mut move_up: Option<usize> = None;
+    let count = calendar.months.len();
+
+    egui::Grid::new("calendar_months_grid")
+    .spacing([10.0, 3.0])
+    .show(ui, |ui| {
+        for (index, month) in calendar.months.iter_mut().enumerate() {
+            ui.label(format!("{}", index + 1));
+            ui.add(egui::TextEdit::singleline(&mut month.name).desired_width(150.0));
+            ui.add(egui::DragValue::new(&mut month.days).clamp_range(1..=1000).suffix(" days"));
+            ui.horizontal(|ui| {
+                if ui.add_enabled(index != 0, egui::Button::new("⏶")).on_hover_text("Move earlier in the year").clicked() {
+                    move_up = Some(index);
+                }
+                if ui.add_enabled(index + 1 != count, egui::Button::new("⏷")).on_hover_text("Move later in the year").clicked() {
+                    move_up = Some(index + 1);
+                }
+                if ui.add_enabled(count > 1, egui::Button::new("Remove")).clicked() {
+                    remove = Some(index);
+                }
+            });
+            ui.end_row();
+        }
+    });
+
+    if ui.button("Add month").clicked() {
+        calendar.months.push(Month { name: format!("Month {}", count + 1), days: 30 });
+    }
+
+    if let Some(index) = move_up {
+        calendar.months.swap(index - 1, index);
+    }
+    if let Some(index) = remove {
+        calendar.months.remove(index);
+        if calendar.start.month >= calendar.months.len() { calendar.start.month = calendar.months.len() - 1; }
+    }
+}
+
+/// Picks a day, month, and year from the calendar's months. Returns `true` in `inner` if the date changed.
+pub(super) fn date_picker(
+    ui: &mut egui::Ui,
+    id_source: &str,
+    date: &mut CalendarDate,
+    months: &[Month],
+) -> egui::InnerResponse<bool> {
+    ui.horizontal(|ui| {
+        let before = *date;
+        let days = months.get(date.month).map_or(1, |month| month.days.max(1));
+
+        ui.add(egui::DragValue::new(&mut date.day).clamp_range(1..=days));
+        egui::ComboBox::from_id_source(format!("{}_month", id_source))
+        .selected_text(months.get(date.month).map_or("", |month| month.name.as_str()))
+        .show_ui(ui, |ui| {
+            for (index, month) in months.iter().enumerate() {
+                ui.selectable_value(&mut date.month, index, &month.name);
+            }
+        });
+        ui.add(egui::DragValue::new(&mut date.year).prefix("year "));
+        date.month = date.month.min(months.len().max(1) - 1);
+
+        *date != before
+    })
+}
@@ -1,11 +1,8 @@
 use bevy::ecs::{system::{CommandQueue, Spawn, Despawn}, prelude::Entity, world::Mut};
 use eframe::egui;
-use crate::{world::{sim::SimulationData, defs::species::{SpeciesBundle, Species}, common::Name, time::Age}, gui::{EntityStringHashable, edit::widgets::{time_length_drag_value, time_length_slider}}};
+use crate::{world::{sim::SimulationData, defs::species::{SpeciesBundle, Species, AgingCurve, MIN_HUMANOID_AGE}, common::Name, time::Age}, gui::{EntityStringHashable, edit::widgets::{time_length_drag_value, time_length_slider}}};
 
 const SOFT_MAX_AGE: Age = Age::from_years(10_000);
-// This is the age at which a humanoid is considered an 'adult', and therefore can reproduce.
-// DO NOT set this lower. No excuses.
-const MIN_HUMANOID_AGE: Age = Age::from_years(18);
 
 pub(super) fn species_menu(
     ui: &mut egui::Ui,
@@ -24,6 +21,9 @@ pub(super) fn species_menu(
                         max_age: Age::from_years(12),
                         resilience: 15.0,
                         immunity: 1.0,
+                        fertility: 1.0,
+                        fertile_until: Age::from_years(10),
+                        aging: AgingCurve::default(),
                     },
                 }
             )});
@@ -40,6 +40,9 @@ pub(super) fn species_menu(
                         max_age: Age::from_years(100),
                         resilience: 100.0,
                         immunity: 1.0,
+                        fertility: 0.2,
+                        fertile_until: Age::from_years(45),
+                        aging: AgingCurve::default(),
                     },
                 }
             )});
@@ -109,6 +112,37 @@ fn species_editor(
             ui.add(time_length_slider(&mut species.maturity_age, range));
             ui.end_row();
 
+            let maturity_age = species.maturity_age;
+
+            // The end of the reproductive years
+            ui.label("Fertile until");
+            species.fertile_until = species.fertile_until.max(maturity_age).min(max_age);
+            ui.add(time_length_slider(&mut species.fertile_until, maturity_age..=max_age));
+            ui.end_row();
+
+            // How often couples have children
+            ui.label("Fertility");
+            ui.add(egui::Slider::new(&mut species.fertility, 0.0..=5.0).suffix(" per year")).on_hover_text(
+                "How many children a couple of this species has each year, on average."
+            );
+            ui.end_row();
+
+            // When old age starts to hurt
+            ui.label("Aging onset");
+            ui.add(egui::Slider::new(&mut species.aging.onset, 0.0..=0.99).custom_formatter(|n, _| {
+                format!("{:.0}% of max age", n * 100.0)
+            })).on_hover_text(
+                "The point at which old age starts harming this creature, as a fraction of its max age."
+            );
+            ui.end_row();
+
+            // How quickly old age hurts
+            ui.label("Aging steepness");
+            ui.add(egui::Slider::new(&mut species.aging.steepness, 0.1..=10.0).logarithmic(true)).on_hover_text(
+                "How sharply harm from old age rises after the onset. 1 is linear, and higher values leave most of the harm until the end."
+            );
+            ui.end_row();
+
             // How much this species can endure before death
             ui.label("Resilience");
             ui.add(egui::Slider::new(&mut species.resilience, 1.0..=100000.0).logarithmic(true).step_by(1.0));
@@ -0,0 +1,259 @@
+//! A window showing a person's family as a graph.
+
+use std::collections::{BTreeMap, VecDeque};
+use bevy::prelude::{Entity, With};
+use eframe::{egui, epaint::{Color32, Stroke, FontId, Pos2, Rect, Vec2}, emath::Align2};
+use crate::{world::{sim::SimulationData, common::Name, living::Living, person::{Person, kinship::{Parents, Partners, Offspring, relationship_between}}}, gui::AppMemory};
+
+use super::TAB_KEY;
+
+/// The size of a person's box at a zoom of 1.
+const NODE_SIZE: Vec2 = Vec2::new(140.0, 36.0);
+/// The distance between the centres of people next to each other.
+const COLUMN_SPACING: f32 = 170.0;
+/// The distance between the centres of generations.
+const ROW_SPACING: f32 = 90.0;
+/// Stops huge families from freezing the UI.
+const MAX_SHOWN: usize = 500;
+
+/// The state of the family tree window.
+pub struct FamilyTreeView {
+    /// The person the tree is built around.
+    root: Entity,
+    /// Whoever was last clicked on.
+    selected: Option<Entity>,
+    pan: Vec2,
+    zoom: f32,
+}
+
+impl FamilyTreeView {
+    pub fn new(root: Entity) -> Self {
+        Self {
+            root,
+            selected: None,
+            pan: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+/// What the tree needs to know about a person.
+struct TreeNode {
+    name: String,
+    dead: bool,
+    parents: Vec<Entity>,
+    partners: Vec<Entity>,
+    offspring: Vec<Entity>,
+}
+
+/// Shows the family tree window, if it's open.
+pub(super) fn family_tree_window(
+    ctx: &egui::Context,
+    memory: &mut AppMemory,
+    sim: &mut SimulationData,
+) {
+    let Some(view) = &mut memory.family_tree else { return; };
+    let world = &mut sim.app.world;
+
+    // Everyone who could be in the tree
+    let mut nodes: BTreeMap<Entity, TreeNode> = BTreeMap::new();
+    for (entity, name, living, parents, partners, offspring) in world.query_filtered::<(Entity, &Name, &Living, Option<&Parents>, Option<&Partners>, Option<&Offspring>), With<Person>>().iter(world) {
+        nodes.insert(entity, TreeNode {
+            name: name.0.clone(),
+            dead: *living == Living::Dead,
+            parents: parents.map(|x| x.0.clone()).unwrap_or_default(),
+            partners: partners.map(|x| x.0.clone()).unwrap_or_default(),
+            offspring: offspring.map(|x| x.0.clone()).unwrap_or_default(),
+        });
+    }
+
+    // The person was deleted
+    if !nodes.contains_key(&view.root) {
+        memory.family_tree = None;
+        return;
+    }
+
+    let positions = layout(&nodes, view.root);
+    let root_position = positions[&view.root];
+
+    let mut open = true;
+    let mut jump_to: Option<Entity> = None;
+
+    egui::Window::new(format!("Family tree of {}", nodes[&view.root].name))
+    .id(egui::Id::new("family_tree_window"))
+    .open(&mut open)
+    .default_size([700.0, 450.0])
+    .resizable(true)
+    .scroll2([false; 2])
+    .show(ctx, |ui| {
+        // Controls and details about the selected person
+        ui.horizontal(|ui| {
+            if ui.button("Reset view").clicked() {
+                view.pan = Vec2::ZERO;
+                view.zoom = 1.0;
+            }
+            ui.label(format!("Showing {} people", positions.len()));
+            if positions.len() >= MAX_SHOWN { ui.label("(limit reached)"); }
+        });
+
+        ui.horizontal(|ui| {
+            match view.selected.filter(|selected| nodes.contains_key(selected)) {
+                Some(selected) => {
+                    let relationship = relationship_between(world, view.root, selected);
+                    ui.label(format!("{} ({:?}), {}", nodes[&selected].name, selected, relationship.describe()));
+                    if selected != view.root && ui.button("Centre tree on them").clicked() {
+                        view.root = selected;
+                        view.pan = Vec2::ZERO;
+                    }
+                    if ui.button("Open in editor").clicked() {
+                        jump_to = Some(selected);
+                    }
+                },
+                None => { ui.label("Click someone to select them. Drag to pan, scroll to zoom."); },
+            }
+        });
+
+        ui.separator();
+
+        // The graph itself
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+
+        view.pan += response.drag_delta();
+        if response.hovered() {
+            let scroll = ui.input(|i| i.scroll_delta.y);
+            view.zoom = (view.zoom * (1.0 + scroll * 0.002)).clamp(0.2, 4.0);
+        }
+
+        let zoom = view.zoom;
+        let origin = rect.center() + view.pan;
+        let to_screen = |position: Vec2| -> Pos2 { origin + (position - root_position) * zoom };
+        let node_rect = |entity: &Entity| Rect::from_center_size(to_screen(positions[entity]), NODE_SIZE * zoom);
+
+        let visuals = ui.visuals();
+        let line_stroke = Stroke::new(1.5 * zoom, visuals.widgets.noninteractive.fg_stroke.color);
+        let partner_stroke = Stroke::new(2.0 * zoom, Color32::from_rgb(200, 120, 160));
+
+        // Lines go underneath people
+        for (entity, position) in positions.iter() {
+            let node = &nodes[entity];
+
+            for child in node.offspring.iter() {
+                let Some(child_position) = positions.get(child) else { continue };
+                let from = to_screen(*position) + Vec2::new(0.0, NODE_SIZE.y * zoom / 2.0);
+                let to = to_screen(*child_position) - Vec2::new(0.0, NODE_SIZE.y * zoom / 2.0);
+                let middle = (from.y + to.y) / 2.0;
+                painter.line_segment([from, Pos2::new(from.x, middle)], line_stroke);
+                painter.line_segment([Pos2::new(from.x, middle), Pos2::new(to.x, middle)], line_stroke);
+                painter.line_segment([Pos2::new(to.x, middle), to], line_stroke);
+            }
+
+            for partner in node.partners.iter() {
+                // Only draw each pair once
+                if partner < entity { continue; }
+                let Some(partner_position) = positions.get(partner) else { continue };
+                painter.line_segment([to_screen(*position), to_screen(*partner_position)], partner_stroke);
+            }
+        }
+
+        // People
+        for entity in positions.keys() {
+            let node = &nodes[entity];
+            let node_rect = node_rect(entity);
+            if !rect.intersects(node_rect) { continue; }
+
+            let (fill, text_color) = match node.dead {
+                true => (visuals.faint_bg_color, visuals.weak_text_color()),
+                false => (visuals.widgets.inactive.bg_fill, visuals.strong_text_color()),
+            };
+            let stroke = if view.selected == Some(*entity) {
+                visuals.selection.stroke
+            } else if *entity == view.root {
+                Stroke::new(2.0, visuals.widgets.active.fg_stroke.color)
+            } else {
+                visuals.widgets.inactive.bg_stroke
+            };
+
+            painter.rect(node_rect, 4.0 * zoom, fill, stroke);
+            painter.text(node_rect.center(), Align2::CENTER_CENTER, &node.name, FontId::proportional(12.0 * zoom), text_color);
+        }
+
+        // Click to select
+        if response.clicked() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                view.selected = positions.keys().find(|entity| node_rect(entity).contains(pointer)).copied();
+            }
+        }
+    });
+
+    if !open { memory.family_tree = None; }
+
+    // Switch to the editor entry of whoever was selected
+    if let Some(entity) = jump_to {
+        memory.string_map.insert(TAB_KEY.to_owned(), "People".to_owned());
+        memory.focus = Some(entity);
+    }
+}
+
+/// Works out where everyone connected to `root` goes, with generations as rows.
+fn layout(nodes: &BTreeMap<Entity, TreeNode>, root: Entity) -> BTreeMap<Entity, Vec2> {
+    // Find generations, relative to the root
+    let mut generations: BTreeMap<Entity, i32> = BTreeMap::from([(root, 0)]);
+    let mut discovered: Vec<Entity> = vec![root];
+    let mut queue: VecDeque<Entity> = VecDeque::from([root]);
+
+    while let Some(entity) = queue.pop_front() {
+        let generation = generations[&entity];
+        let node = &nodes[&entity];
+
+        let related = node.parents.iter().map(|e| (*e, generation - 1))
+            .chain(node.partners.iter().map(|e| (*e, generation)))
+            .chain(node.offspring.iter().map(|e| (*e, generation + 1)));
+
+        for (other, generation) in related {
+            if discovered.len() >= MAX_SHOWN { break; }
+            if generations.contains_key(&other) || !nodes.contains_key(&other) { continue; }
+            generations.insert(other, generation);
+            discovered.push(other);
+            queue.push_back(other);
+        }
+    }
+
+    // Group into rows, in the order people were found
+    let mut rows: BTreeMap<i32, Vec<Entity>> = BTreeMap::new();
+    for entity in discovered.iter() {
+        rows.entry(generations[entity]).or_default().push(*entity);
+    }
+
+    // Going down the generations, put children under their parents and partners next to each other
+    let mut positions: BTreeMap<Entity, Vec2> = BTreeMap::new();
+    for (generation, row) in rows.iter_mut() {
+        let key = |entity: &Entity, index: usize| -> f32 {
+            let placed: Vec<f32> = nodes[entity].parents.iter().filter_map(|parent| positions.get(parent)).map(|p| p.x).collect();
+            match placed.is_empty() {
+                true => (index as f32 - (row.len() as f32 - 1.0) / 2.0) * COLUMN_SPACING,
+                false => placed.iter().sum::<f32>() / placed.len() as f32,
+            }
+        };
+
+        let mut keyed: Vec<(f32, Entity)> = row.iter().enumerate().map(|(index, entity)| (key(entity, index), *entity)).collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut ordered: Vec<Entity> = Vec::with_capacity(row.len());
+        for (_, entity) in keyed.iter() {
+            if ordered.contains(entity) { continue; }
+            ordered.push(*entity);
+            for partner in nodes[entity].partners.iter() {
+                if row.contains(partner) && !ordered.contains(partner) { ordered.push(*partner); }
+            }
+        }
+
+        // Centre each row
+        let offset = (ordered.len() as f32 - 1.0) / 2.0;
+        for (index, entity) in ordered.iter().enumerate() {
+            positions.insert(*entity, Vec2::new((index as f32 - offset) * COLUMN_SPACING, *generation as f32 * ROW_SPACING));
+        }
+    }
+
+    positions
+}
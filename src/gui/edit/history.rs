@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet};
+use bevy::{ecs::system::CommandQueue, prelude::{Entity, With, Or}};
+use eframe::egui;
+use crate::{world::{calendar::Calendar, sim::SimulationData, event::{History, HistoryEvent, HistoryEventKind}, common::Name, person::Person, place::{Region, Settlement}, time::Age}, gui::AppMemory};
+
+use super::{TAB_KEY, widgets::time_length_drag_value};
+
+const SEARCH_KEY: &str = "edit_history_search";
+
+/// Filters for the history tab that can't be stored as strings.
+pub struct HistoryFilter {
+    place: Option<Entity>,
+    hidden_kinds: BTreeSet<HistoryEventKind>,
+    start: Age,
+    end: Age,
+}
+
+impl Default for HistoryFilter {
+    fn default() -> Self {
+        Self {
+            place: None,
+            hidden_kinds: BTreeSet::new(),
+            start: Age::ZERO,
+            end: Age::from_years(100_000),
+        }
+    }
+}
+
+impl HistoryFilter {
+    fn matches(&self, event: &HistoryEvent, names: &BTreeMap<Entity, String>, search_term: &str) -> bool {
+        if self.hidden_kinds.contains(&event.kind) { return false; }
+        if event.date < self.start || event.date > self.end { return false; }
+        if self.place.is_some() && event.place != self.place { return false; }
+
+        // Search participant names
+        if !search_term.is_empty() {
+            return event.participants.iter().any(|participant| {
+                names.get(participant).is_some_and(|name| name.to_lowercase().contains(search_term))
+            });
+        }
+
+        true
+    }
+}
+
+pub(super) fn edit_history_ui(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+    _queue: &mut CommandQueue,
+    sim: &mut SimulationData,
+) {
+    let world = &mut sim.app.world;
+    let calendar = world.get_resource::<Calendar>().cloned().unwrap_or_default();
+
+    // Names of everything, for display and searching
+    let mut names: BTreeMap<Entity, String> = BTreeMap::new();
+    for (entity, name) in world.query::<(Entity, &Name)>().iter(world) {
+        names.insert(entity, name.0.clone());
+    }
+
+    let mut people: BTreeSet<Entity> = BTreeSet::new();
+    for entity in world.query_filtered::<Entity, With<Person>>().iter(world) {
+        people.insert(entity);
+    }
+
+    let mut places: BTreeSet<Entity> = BTreeSet::new();
+    for entity in world.query_filtered::<Entity, Or<(With<Region>, With<Settlement>)>>().iter(world) {
+        places.insert(entity);
+    }
+
+    // Filters
+    memory.string_map.entry(SEARCH_KEY.to_string()).or_default();
+    let filter = &mut memory.history_filter;
+
+    egui::Grid::new("history_filters")
+    .spacing([10.0, 3.0])
+    .show(ui, |ui| {
+        ui.label("Participant");
+        egui::TextEdit::singleline(memory.string_map.get_mut(SEARCH_KEY).unwrap()).hint_text("Enter a name...").show(ui);
+        ui.end_row();
+
+        ui.label("Place");
+        let selected_text = match filter.place {
+            Some(place) => names.get(&place).cloned().unwrap_or(format!("{:?}", place)),
+            None => "Anywhere".to_string(),
+        };
+        egui::ComboBox::from_id_source("history_place_filter")
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut filter.place, None, "Anywhere");
+            for place in places.iter() {
+                let name = names.get(place).cloned().unwrap_or_default();
+                ui.selectable_value(&mut filter.place, Some(*place), format!("{} ({:?})", name, place));
+            }
+        });
+        ui.end_row();
+
+        ui.label("Kinds");
+        ui.horizontal_wrapped(|ui| {
+            for kind in HistoryEventKind::ALL {
+                let mut shown = !filter.hidden_kinds.contains(&kind);
+                if ui.checkbox(&mut shown, kind.name()).changed() {
+                    if shown { filter.hidden_kinds.remove(&kind); } else { filter.hidden_kinds.insert(kind); }
+                }
+            }
+        });
+        ui.end_row();
+
+        ui.label("Between");
+        ui.horizontal(|ui| {
+            ui.add(time_length_drag_value(&mut filter.start));
+            ui.label("and");
+            ui.add(time_length_drag_value(&mut filter.end));
+        });
+        ui.end_row();
+    });
+
+    ui.separator();
+
+    let Some(history) = world.get_resource::<History>() else {
+        ui.label("This world has no history.");
+        return;
+    };
+
+    // Chronological order, keeping recording order for events on the same date
+    let search_term = memory.string_map.get(SEARCH_KEY).unwrap().to_lowercase();
+    let mut events: Vec<&HistoryEvent> = history.iter()
+        .filter(|event| memory.history_filter.matches(event, &names, &search_term))
+        .collect();
+    events.sort_by_key(|event| (event.date, event.tick));
+
+    ui.label(format!("Showing {} of {} events", events.len(), history.len()));
+
+    let mut jump_to: Option<Entity> = None;
+    let row_height = ui.spacing().interact_size.y;
+
+    egui::ScrollArea::both()
+    .id_source("history_scroll_area")
+    .auto_shrink([false, false])
+    .show_rows(ui, row_height, events.len(), |ui, range| {
+        for event in &events[range] {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(calendar.format_after(event.date)).monospace())
+                    .on_hover_text(format!("{} after the start, on tick {}", event.date, event.tick));
+                ui.label(egui::RichText::new(event.kind.name()).strong());
+                ui.label(&event.summary);
+
+                // Links to editor entries
+                for participant in event.participants.iter() {
+                    if !people.contains(participant) && !places.contains(participant) { continue; }
+                    let name = names.get(participant).cloned().unwrap_or_default();
+                    if ui.link(name).clicked() {
+                        jump_to = Some(*participant);
+                    }
+                }
+            });
+        }
+    });
+
+    // Switch to the editor entry of whatever was clicked
+    if let Some(entity) = jump_to {
+        let tab = if people.contains(&entity) { "People" } else { "Places" };
+        memory.string_map.insert(TAB_KEY.to_owned(), tab.to_owned());
+        memory.focus = Some(entity);
+    }
+}
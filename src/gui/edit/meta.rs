@@ -1,6 +1,15 @@
-use bevy::ecs::{system::CommandQueue, world::Mut};
+use std::str::FromStr;
+use bevy::ecs::{system::CommandQueue, world::Mut, query::With};
 use eframe::egui;
-use crate::{world::{sim::SimulationData, defs::{SimulationConfig, HistoryDirection, Timespan}}, gui::AppMemory};
+use super::{calendar::{calendar_settings, date_picker}, stop::{StopSubjects, stop_condition_settings}};
+use crate::{world::{calendar::Calendar, common::Name, faction::Faction, person::Person, place::Settlement, season::{Season, Seasons}, stop::StopConditions, sim::{SimulationData, MAX_CHECKPOINTS}, defs::{SimulationConfig, HistoryDirection, Timespan}, soft_limits::{self, MIN_YEARS_TO_SIMULATE, MAX_YEARS_TO_SIMULATE}, time::{Age, format_duration}}, gui::{AppMemory, branches::{BRANCH_TARGET_KEY, NEW_BRANCH_KEY}}};
+
+/// The run length being typed in, before it's been read.
+const RUN_LENGTH_KEY: &str = "edit_meta_run_length";
+/// Present if the run ends on a date picked from the calendar, rather than after a length of time from now.
+const RUN_UNTIL_KEY: &str = "edit_meta_run_until";
+/// The end date that was picked, as days after the start. This can be earlier than the run actually ends, since runs are whole steps long.
+const RUN_END_KEY: &str = "edit_meta_run_end";
 
 pub(super) fn edit_meta_ui(
     ui: &mut egui::Ui,
@@ -8,9 +17,12 @@ pub(super) fn edit_meta_ui(
     _queue: &mut CommandQueue,
     sim: &mut SimulationData,
 ) {
+    let calendar = sim.app.world.get_resource::<Calendar>().cloned().unwrap_or_default();
+    let previous_seasons = sim.app.world.get_resource::<Seasons>().cloned().unwrap_or_default();
+    let mut seasons = previous_seasons.clone();
     let mut config = sim.app.world.resource_mut::<SimulationConfig>();
 
-    ui.add_enabled_ui(!config.locked_in, |ui| world_settings(ui, &mut config));
+    ui.add_enabled_ui(!config.locked_in, |ui| world_settings(ui, memory, &mut config, &mut seasons, &calendar));
 
     ui.separator();
 
@@ -21,6 +33,10 @@ pub(super) fn edit_meta_ui(
             }
         });
 
+        if ui.button("Check for problems").clicked() {
+            memory.markers.insert("validate_world".to_string());
+        }
+
         ui.add_enabled_ui(config.increments_completed != 0, |ui| {
             if ui.button("Reset incrementor").clicked() {
                 config.increments_completed = 0;
@@ -28,12 +44,88 @@ pub(super) fn edit_meta_ui(
         });
 
         ui.label(format!("{} out of {} steps complete", config.increments_completed, config.increments_for_completion));
+        ui.label(format!("It is {}", calendar.format(config.current_date(&calendar))));
+    });
+
+    // Only written back when edited, so change detection stays meaningful
+    if seasons != previous_seasons {
+        sim.app.world.insert_resource(seasons);
+    }
+
+    ui.separator();
+
+    branches(ui, memory);
+
+    ui.separator();
+
+    egui::CollapsingHeader::new("Calendar")
+    .id_source("calendar_settings")
+    .show(ui, |ui| {
+        let mut calendar = sim.app.world.get_resource_or_insert_with(Calendar::default);
+        calendar_settings(ui, &mut calendar);
+    });
+
+    egui::CollapsingHeader::new("Stop conditions")
+    .id_source("stop_conditions")
+    .show(ui, |ui| {
+        let world = &mut sim.app.world;
+        let subjects = StopSubjects {
+            people: world.query_filtered::<&Name, With<Person>>().iter(world).map(|name| name.0.clone()).collect(),
+            settlements: world.query_filtered::<&Name, With<Settlement>>().iter(world).map(|name| name.0.clone()).collect(),
+            factions: world.query_filtered::<&Name, With<Faction>>().iter(world).map(|name| name.0.clone()).collect(),
+        };
+
+        let mut conditions = world.get_resource_or_insert_with(StopConditions::default);
+        stop_condition_settings(ui, &mut conditions, &subjects);
+    });
+}
+
+fn branches(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Branch");
+
+        let mut selected = memory.current_branch.clone();
+        egui::ComboBox::from_id_source("branch_selection")
+        .selected_text(&selected)
+        .show_ui(ui, |ui| {
+            for name in memory.branch_names.iter() {
+                ui.selectable_value(&mut selected, name.clone(), name);
+            }
+        }).response.on_hover_text(
+            "Branches are alternate histories of the same world. Switching keeps the current branch as it is, so it can be come back to."
+        );
+        if selected != memory.current_branch {
+            memory.string_map.insert(BRANCH_TARGET_KEY.to_string(), selected);
+            memory.markers.insert("switch_branch".to_string());
+        }
+
+        let name = memory.string_map.entry(NEW_BRANCH_KEY.to_string()).or_default();
+        egui::TextEdit::singleline(name).hint_text("New branch name").desired_width(120.0).show(ui);
+        let can_fork = !name.trim().is_empty() && !memory.branch_names.iter().any(|existing| existing == name.trim());
+        ui.add_enabled_ui(can_fork, |ui| {
+            if ui.button("Fork").on_hover_text(
+                "Starts a new branch from how this branch was before it was last simulated, or from the world as it is if it hasn't been simulated yet."
+            ).clicked() {
+                memory.markers.insert("fork_branch".to_string());
+            }
+        });
+
+        if ui.button("Compare branches").clicked() {
+            memory.markers.insert("branch_comparison_open".to_string());
+            memory.markers.insert("compare_branches".to_string());
+        }
     });
 }
 
 fn world_settings(
     ui: &mut egui::Ui,
+    memory: &mut AppMemory,
     config: &mut Mut<SimulationConfig>,
+    seasons: &mut Seasons,
+    calendar: &Calendar,
 ) {
     egui::ScrollArea::both()
     .id_source("world_settings_scroll")
@@ -76,32 +168,231 @@ Generating backwards will generate the history leading to the present, explainin
             );
             ui.end_row();
 
-            // History step length
+            // History step length, which keeps the same run length when it's changed
+            // Steps that have already run would change length too, moving the dates of everything they recorded
             ui.label("Timespan");
-            egui::ComboBox::from_id_source("world_settings_timespan")
-            .selected_text(format!("{:?}", config.timespan))
-            .show_ui(ui, |ui| {
-                ui.selectable_value(&mut config.timespan, Timespan::Months, "Months");
-                ui.selectable_value(&mut config.timespan, Timespan::Days, "Days");
-            }).response.on_hover_text(
-                "The span of time each tick of the simulator works at.
-Ticking by months will finish simulating faster, but provides less detail, and is best chosen for large periods of time.
-Ticking by days simulates slowly, but provides a lot of detail, and is best chosen for smaller periods of time."
-            );
+            let length = config.run_length();
+            let timespan = config.timespan.clone();
+            let started = config.increments_completed != 0;
+            ui.add_enabled_ui(!started, |ui| {
+                egui::ComboBox::from_id_source("world_settings_timespan")
+                .selected_text(format!("{:?}", config.timespan))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut config.timespan, Timespan::Days, "Days");
+                    ui.selectable_value(&mut config.timespan, Timespan::Weeks, "Weeks");
+                    ui.selectable_value(&mut config.timespan, Timespan::Months, "Months");
+                    ui.selectable_value(&mut config.timespan, Timespan::Seasons, "Seasons");
+                    ui.selectable_value(&mut config.timespan, Timespan::Years, "Years");
+                }).response.on_hover_text(
+                    "The span of time each tick of the simulator works at.
+Ticking by years or seasons will finish simulating faster, but provides less detail, and is best chosen for large periods of time.
+Ticking by days or weeks simulates slowly, but provides a lot of detail, and is best chosen for smaller periods of time."
+                ).on_disabled_hover_text(
+                    "The timespan can't be changed once some steps have run, since it would move the dates of everything that's happened. Reset the incrementor to change it."
+                );
+            });
+            if config.timespan != timespan { config.set_run_length(length); }
+            ui.end_row();
+
+            // Run length
+            ui.label("Run length");
+            run_length(ui, memory, config, calendar);
             ui.end_row();
 
-            // Simulation steps
-            ui.label(format!("{:?} to simulate", config.timespan));
+            // Checkpoints
+            ui.label("Checkpoint every");
             ui.horizontal(|ui| {
-                ui.add(egui::Slider::new(&mut config.increments_for_completion, 10..=10_000).logarithmic(true));
-                const STEPS_BEFORE_WARNING: u32 = 1000;
-                if config.increments_for_completion > STEPS_BEFORE_WARNING {
-                    ui.label(egui::RichText::from("⚠").color(egui::Color32::RED)).on_hover_text(
-                        format!("Setting the amount of steps in the simulation above {} may take a long time.", STEPS_BEFORE_WARNING)
-                    );
-                }
+                ui.add(egui::DragValue::new(&mut config.checkpoint_interval).clamp_range(0..=10_000));
+                ui.label(format!("{:?}", config.timespan).to_lowercase());
+            }).response.on_hover_text(format!(
+                "How often a copy of the world is kept while simulating, which can be restored from the Simulation menu afterwards.
+Set this to zero to turn checkpoints off. Only the latest {} checkpoints are kept, and each one takes up memory.",
+                MAX_CHECKPOINTS,
+            ));
+            ui.end_row();
+
+            // Seasons
+            ui.label("Seasons").on_hover_text(
+                "Parts of the year that change how the world behaves. Each season lasts from the month it starts in until the next season starts.
+Affliction modifiers above 1 make afflictions worsen faster and heal slower, like a harsh winter.
+Growth modifiers above 1 make children more likely to be born, like after a good harvest."
+            );
+            season_table(ui, seasons, calendar);
+            ui.end_row();
+        });
+    });
+}
+
+/// Lets the user set how long to simulate for, either as a length of time from now, or as the time since the start to end at.
+fn run_length(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+    config: &mut Mut<SimulationConfig>,
+    calendar: &Calendar,
+) {
+    let until = memory.markers.contains(RUN_UNTIL_KEY);
+
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            let mut selected = until;
+            egui::ComboBox::from_id_source("world_settings_run_mode")
+            .width(60.0)
+            .selected_text(if until { "Until" } else { "For" })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, false, "For").on_hover_text("Simulate for this long from now.");
+                ui.selectable_value(&mut selected, true, "Until").on_hover_text("Simulate until this date in the calendar.");
             });
+            if selected != until {
+                if selected { memory.markers.insert(RUN_UNTIL_KEY.to_string()); } else { memory.markers.remove(RUN_UNTIL_KEY); }
+                memory.string_map.remove(RUN_LENGTH_KEY);
+                memory.string_map.remove(RUN_END_KEY);
+            }
+
+            match selected {
+                true => run_until(ui, memory, config, calendar),
+                false => run_for(ui, memory, config),
+            }
+        }).response.on_hover_text(format!(
+            "How long to simulate for, in in-world time. This must be between {} and {} years in total.",
+            MIN_YEARS_TO_SIMULATE, MAX_YEARS_TO_SIMULATE,
+        ));
+
+        let steps = config.increments_for_completion;
+        let timespan = format!("{:?}", config.timespan).to_lowercase();
+        ui.horizontal(|ui| {
+            match config.direction {
+                HistoryDirection::Forwards => ui.label(format!("Ends on {}, after {} {}", calendar.format_after(config.run_length()), steps, timespan)),
+                HistoryDirection::Backwards => ui.label(format!(
+                    "Goes back from {} to {}, over {} {}",
+                    calendar.format_after(config.run_length()), calendar.format_after(Age::ZERO), steps, timespan,
+                )),
+            };
+
+            const STEPS_BEFORE_WARNING: u32 = 1000;
+            if steps > STEPS_BEFORE_WARNING {
+                ui.label(egui::RichText::from("⚠").color(egui::Color32::RED)).on_hover_text(
+                    format!("Setting the amount of steps in the simulation above {} may take a long time.", STEPS_BEFORE_WARNING)
+                );
+            }
+        });
+
+        // Based on the last run, so it's only a rough guess
+        let remaining = steps.saturating_sub(config.increments_completed);
+        match memory.tick_time {
+            Some(tick_time) => ui.label(format!("About {} to simulate", format_duration(tick_time * remaining))),
+            None => ui.label("Run the simulation to estimate how long it will take."),
+        };
+    });
+}
+
+/// Reads a length of time to simulate for from now.
+fn run_for(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+    config: &mut Mut<SimulationConfig>,
+) {
+    let elapsed = config.time_elapsed();
+    let current = Age::from_days(config.run_length().days_passed().saturating_sub(elapsed.days_passed()));
+
+    let text = memory.string_map.entry(RUN_LENGTH_KEY.to_string()).or_insert_with(|| current.to_string());
+    let response = ui.add(egui::TextEdit::singleline(text).hint_text("e.g. 200 years").desired_width(150.0));
+    let parsed = Age::from_str(text.trim());
+
+    if response.changed() {
+        if let Ok(length) = parsed {
+            config.set_run_length(Age::from_days(elapsed.days_passed().saturating_add(length.days_passed())));
+        }
+    }
+
+    if parsed.is_err() {
+        ui.label(egui::RichText::from("⚠").color(egui::Color32::RED)).on_hover_text(
+            "This isn't a length of time the simulation understands. Write it like '200 years 6 months 10 days'."
+        );
+    }
+
+    // Show what was actually used once the user is done typing, since it might have been limited
+    if !response.has_focus() && parsed.is_ok() {
+        memory.string_map.remove(RUN_LENGTH_KEY);
+    }
+}
+
+/// Picks a date in the calendar to simulate until.
+fn run_until(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+    config: &mut Mut<SimulationConfig>,
+    calendar: &Calendar,
+) {
+    let days_to_age = |days: i64| Age::from_days(days.clamp(0, u32::MAX as i64) as u32);
+
+    // The picked date is kept while the run still ends on it, so rounding up to a whole step doesn't move it
+    let picked = memory.string_map.get(RUN_END_KEY)
+        .and_then(|days| days.parse::<i64>().ok())
+        .filter(|days| config.increments_for(soft_limits::clamp(days_to_age(*days))) == config.increments_for_completion);
+    let mut end = calendar.date_offset(picked.unwrap_or(config.run_length().days_passed() as i64));
+
+    if date_picker(ui, "world_settings_run_end", &mut end, &calendar.months).inner {
+        let days = calendar.days_until(end);
+        memory.string_map.insert(RUN_END_KEY.to_string(), days.to_string());
+        config.set_run_length(days_to_age(days));
+    }
+}
+
+fn season_table(
+    ui: &mut egui::Ui,
+    seasons: &mut Seasons,
+    calendar: &Calendar,
+) {
+    let month_name = |index: usize| match calendar.months.get(index) {
+        Some(month) => month.name.clone(),
+        None => format!("Month {}", index + 1),
+    };
+
+    ui.vertical(|ui| {
+        let mut remove: Option<usize> = None;
+
+        egui::Grid::new("world_settings_seasons")
+        .spacing([10.0, 3.0])
+        .show(ui, |ui| {
+            ui.label("Name");
+            ui.label("Starts in");
+            ui.label("Afflictions");
+            ui.label("Growth");
             ui.end_row();
+
+            for (index, season) in seasons.0.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut season.name).desired_width(100.0));
+                egui::ComboBox::from_id_source(("world_settings_season_month", index))
+                .selected_text(month_name(season.first_month))
+                .show_ui(ui, |ui| {
+                    for month in 0..calendar.months.len() {
+                        ui.selectable_value(&mut season.first_month, month, month_name(month));
+                    }
+                });
+                ui.add(egui::DragValue::new(&mut season.affliction_modifier).clamp_range(0.0..=10.0).speed(0.01).prefix("×"));
+                ui.add(egui::DragValue::new(&mut season.growth_modifier).clamp_range(0.0..=10.0).speed(0.01).prefix("×"));
+                if ui.button("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
         });
+
+        if let Some(index) = remove {
+            seasons.0.remove(index);
+        }
+
+        if ui.button("Add season").clicked() {
+            // Start in the first month without a season, if there is one
+            let first_month = (0..calendar.months.len())
+                .find(|month| seasons.0.iter().all(|season| season.first_month != *month))
+                .unwrap_or(0);
+            seasons.0.push(Season {
+                name: format!("Season {}", seasons.0.len() + 1),
+                first_month,
+                affliction_modifier: 1.0,
+                growth_modifier: 1.0,
+            });
+        }
     });
 }
\ No newline at end of file
@@ -4,6 +4,10 @@ mod meta;
 mod people;
 mod definitions;
 mod places;
+mod history;
+mod family_tree;
+mod calendar;
+mod stop;
 mod helpers;
 
 use bevy::ecs::system::CommandQueue;
@@ -15,11 +19,16 @@ use self::{
     people::edit_people_ui,
     definitions::edit_definitions_ui,
     places::edit_places_ui,
+    history::edit_history_ui,
+    family_tree::family_tree_window,
 };
 
+pub(super) use self::history::HistoryFilter;
+pub(super) use self::family_tree::FamilyTreeView;
+
 use super::AppMemory;
 
-const TAB_KEY: &'static str = "edit_current_tab";
+pub(super) const TAB_KEY: &'static str = "edit_current_tab";
 
 pub(super) fn edit_ui(
     ui: &mut egui::Ui,
@@ -38,6 +47,7 @@ pub(super) fn edit_ui(
             ui.selectable_value(current_tab, "People".to_owned(), "People");
             ui.selectable_value(current_tab, "Definitions".to_owned(), "Definitions");
             ui.selectable_value(current_tab, "Places".to_owned(), "Places");
+            ui.selectable_value(current_tab, "History".to_owned(), "History");
         });
     });
 
@@ -49,6 +59,10 @@ pub(super) fn edit_ui(
         "People" => edit_people_ui(ui, memory, queue, sim),
         "Definitions" => edit_definitions_ui(ui, memory, queue, sim),
         "Places" => edit_places_ui(ui, memory, queue, sim),
+        "History" => edit_history_ui(ui, memory, queue, sim),
         _ => todo!("Handle this case"),
     }
+
+    // Windows that can be opened from any tab
+    family_tree_window(&ui.ctx().clone(), memory, sim);
 }
\ No newline at end of file
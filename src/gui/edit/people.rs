@@ -1,9 +1,9 @@
 use std::{collections::{BTreeMap, BTreeSet}, marker::PhantomData};
 use bevy::ecs::{system::{CommandQueue, Spawn, Insert, Remove, Despawn}, query::With, prelude::Entity};
 use eframe::egui;
-use crate::{world::{sim::SimulationData, person::{PersonBundle, Person, Personality}, common::{Name, Important}, defs::species::{Species, AssociatedSpecies}, living::{Living, health::CachedHealth}, time::Age}, gui::{EntityStringHashable, AppMemory}};
+use crate::{world::{sim::SimulationData, person::{PersonBundle, Person, Personality, kinship::{Parents, Partners, Offspring, LinkParent, UnlinkParent, LinkPartners, UnlinkPartners}}, common::{Name, Important}, defs::species::{Species, AssociatedSpecies}, living::{Living, health::CachedHealth}, time::Age}, gui::{EntityStringHashable, AppMemory}};
 
-use super::widgets::{time_length_drag_value, time_length_slider};
+use super::{FamilyTreeView, widgets::{time_length_drag_value, time_length_slider}};
 
 const SEARCH_KEY: &str = "edit_people_search";
 
@@ -13,6 +13,9 @@ pub(super) fn edit_people_ui(
     queue: &mut CommandQueue,
     sim: &mut SimulationData,
 ) {
+    // Make sure a focused person isn't hidden by the search
+    if memory.focus.is_some() { memory.string_map.insert(SEARCH_KEY.to_string(), "".to_string()); }
+
     ui.horizontal(|ui| {
         if ui.button("Add person").clicked() {
             queue.push(Spawn { bundle: (
@@ -63,6 +66,22 @@ fn character_editor(
         species_map.insert(entity, (name.clone(), species.clone()));
     }
 
+    // Names and family of everyone, since the people query is borrowed mutably in the loop
+    let mut people_names: Vec<(Entity, String)> = vec![];
+    for (entity, name) in sim.app.world.query_filtered::<(Entity, &Name), With<Person>>().iter(&sim.app.world) {
+        people_names.push((entity, name.0.clone()));
+    }
+
+    let mut kin_map: BTreeMap<Entity, Kin> = BTreeMap::new();
+    for (entity, parents, partners, offspring) in sim.app.world.query::<(Entity, Option<&Parents>, Option<&Partners>, Option<&Offspring>)>().iter(&sim.app.world) {
+        kin_map.insert(entity, Kin {
+            parents: parents.map(|x| x.0.clone()).unwrap_or_default(),
+            partners: partners.map(|x| x.0.clone()).unwrap_or_default(),
+            offspring: offspring.map(|x| x.0.clone()).unwrap_or_default(),
+        });
+    }
+
+    let focus = memory.focus.take();
     let search_term = memory.string_map.get(SEARCH_KEY);
 
     egui::ScrollArea::both()
@@ -81,14 +100,20 @@ fn character_editor(
             }
 
             // New header for each person
-            egui::CollapsingHeader::new(format!("{} ({:?})", name.0, entity))
+            let focused = focus == Some(entity);
+            let header = egui::CollapsingHeader::new(format!("{} ({:?})", name.0, entity))
             .id_source(EntityStringHashable(entity, "person_cfg".to_string()))
+            .open(if focused { Some(true) } else { None })
             .show(ui, |ui| {
                 // Danger zone buttons
                 ui.horizontal(|ui| {
                     if ui.button("Delete person").clicked() {
                         queue.push(Despawn { entity });
                     }
+
+                    if ui.button("Family tree").clicked() {
+                        memory.family_tree = Some(FamilyTreeView::new(entity));
+                    }
                 });
 
                 ui.add_space(3.0);
@@ -212,8 +237,97 @@ fn character_editor(
                         ui.add(time_length_drag_value(&mut age).clamp_range(Age::ZERO..=max_age));
                     }
                     ui.end_row();
+
+                    // Family
+                    let Kin { parents, partners, offspring } = kin_map.get(&entity).cloned().unwrap_or_default();
+
+                    ui.label("Parents");
+                    ui.vertical(|ui| {
+                        for parent in parents.iter() {
+                            if kin_entry(ui, &people_names, *parent) {
+                                queue.push(UnlinkParent { child: entity, parent: *parent });
+                            }
+                        }
+                        if let Some(parent) = kin_picker(ui, &people_names, entity, &parents, "Add parent") {
+                            queue.push(LinkParent { child: entity, parent });
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Partners");
+                    ui.vertical(|ui| {
+                        for partner in partners.iter() {
+                            if kin_entry(ui, &people_names, *partner) {
+                                queue.push(UnlinkPartners(entity, *partner));
+                            }
+                        }
+                        if let Some(partner) = kin_picker(ui, &people_names, entity, &partners, "Add partner") {
+                            queue.push(LinkPartners(entity, partner));
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Children");
+                    ui.vertical(|ui| {
+                        if offspring.is_empty() { ui.label("None"); }
+                        for child in offspring.iter() {
+                            ui.label(kin_name(&people_names, *child));
+                        }
+                    });
+                    ui.end_row();
                 });
             });
+
+            if focused { header.header_response.scroll_to_me(Some(egui::Align::Center)); }
+        }
+    });
+}
+
+/// Copies of a person's family components.
+#[derive(Clone, Default)]
+struct Kin {
+    parents: Vec<Entity>,
+    partners: Vec<Entity>,
+    offspring: Vec<Entity>,
+}
+
+/// Returns the name of a relative, or a placeholder if they no longer exist.
+fn kin_name(people: &[(Entity, String)], entity: Entity) -> String {
+    match people.iter().find(|(person, _)| *person == entity) {
+        Some((_, name)) => format!("{} ({:?})", name, entity),
+        None => format!("(deleted) ({:?})", entity),
+    }
+}
+
+/// Shows a relative with a remove button, returning true if it was clicked.
+fn kin_entry(ui: &mut egui::Ui, people: &[(Entity, String)], entity: Entity) -> bool {
+    let mut clicked = false;
+    ui.horizontal(|ui| {
+        ui.label(kin_name(people, entity));
+        clicked = ui.small_button("Remove").clicked();
+    });
+    clicked
+}
+
+/// A dropdown of people that aren't `entity` or already in `existing`, returning whoever was picked.
+fn kin_picker(
+    ui: &mut egui::Ui,
+    people: &[(Entity, String)],
+    entity: Entity,
+    existing: &[Entity],
+    text: &str,
+) -> Option<Entity> {
+    let mut picked = None;
+    egui::ComboBox::from_id_source(EntityStringHashable(entity, text.to_string()))
+    .selected_text(text)
+    .width(150.0)
+    .show_ui(ui, |ui| {
+        for (person, name) in people {
+            if *person == entity || existing.contains(person) { continue; }
+            if ui.button(format!("{} ({:?})", name, person)).clicked() {
+                picked = Some(*person);
+            }
         }
     });
-}
\ No newline at end of file
+    picked
+}
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use bevy::{ecs::system::{CommandQueue, Spawn}, prelude::{Or, Entity, With, Parent, Children, QueryState, Without, World, DespawnRecursive}};
 use eframe::{egui, epaint::Color32};
 use crate::{world::{sim::SimulationData, place::{Settlement, Region, RegionBundle, SettlementBundle}, common::Name}, gui::{EntityStringHashable, ecs::SpawnChild, AppMemory}};
@@ -13,6 +13,9 @@ pub(super) fn edit_places_ui(
     queue: &mut CommandQueue,
     sim: &mut SimulationData,
 ) {
+    // Make sure a focused place isn't hidden by the search
+    if memory.focus.is_some() { memory.string_map.insert(SEARCH_KEY.to_string(), "".to_string()); }
+
     ui.horizontal(|ui| {
         if ui.button("New region").clicked() {
             queue.push(Spawn { bundle: RegionBundle::default() });
@@ -33,6 +36,14 @@ pub(super) fn edit_places_ui(
 
     let world = &mut sim.app.world;
 
+    // The focused place and everything above it should be opened
+    let mut opened: BTreeSet<Entity> = BTreeSet::new();
+    let mut next = memory.focus.take();
+    while let Some(entity) = next {
+        if !opened.insert(entity) { break; }
+        next = world.get::<Parent>(entity).map(|parent| parent.get());
+    }
+
     let mut all_nodes = world.query_filtered::<(Entity, &Name, Option<&Parent>, Option<&Children>), Or<(With<Region>, With<Settlement>)>>();
 
     let ilen = all_nodes.iter(&world).len();
@@ -83,30 +94,44 @@ pub(super) fn edit_places_ui(
     .id_source("places_scroll_area")
     .auto_shrink([false, false])
     .show(ui, |ui| {
+        let mut tree = PlaceTree {
+            subnodes: &subnodes,
+            opened: &opened,
+            region_list: &all_regions,
+            regions: &mut regions,
+            settlements: &mut settlements,
+        };
         for root in &roots {
-            recursively_create_ui(*root, &subnodes, queue, ui, world, &all_regions, &mut regions, &mut settlements);
+            recursively_create_ui(*root, &mut tree, queue, ui, world);
         }
     });
 }
 
+/// The place hierarchy, and the queries for showing each place in it.
+struct PlaceTree<'a> {
+    subnodes: &'a BTreeMap<Entity, Vec<Entity>>,
+    opened: &'a BTreeSet<Entity>,
+    region_list: &'a Vec<(Entity, String)>,
+    regions: &'a mut QueryState<(Entity, &'static mut Name, &'static mut Region), Without<Settlement>>,
+    settlements: &'a mut QueryState<(Entity, &'static mut Name, &'static mut Settlement), Without<Region>>,
+}
+
 fn recursively_create_ui(
     element: Entity,
-    subnodes: &BTreeMap<Entity, Vec<Entity>>,
+    tree: &mut PlaceTree,
     queue: &mut CommandQueue,
     ui: &mut egui::Ui,
     world: &mut World,
-    region_list: &Vec<(Entity, String)>,
-    regions: &mut QueryState<(Entity, &mut Name, &mut Region), Without<Settlement>>,
-    settlements: &mut QueryState<(Entity, &mut Name, &mut Settlement), Without<Region>>,
 ) {
+    let (subnodes, opened, region_list) = (tree.subnodes, tree.opened, tree.region_list);
     let header_title: String;
 
-    match regions.get(world, element) {
+    match tree.regions.get(world, element) {
         Ok((_, name, _)) => {
             header_title = name.0.clone();
         },
         Err(_) => {
-            match settlements.get(world, element) {
+            match tree.settlements.get(world, element) {
                 Ok((_, name, _)) => {
                     header_title = name.0.clone();
                 },
@@ -117,15 +142,16 @@ fn recursively_create_ui(
         },
     }
 
-    egui::CollapsingHeader::new(format!("{} ({:?})", header_title, element))
+    let header = egui::CollapsingHeader::new(format!("{} ({:?})", header_title, element))
     .id_source(EntityStringHashable(element, "place_config".to_string()))
+    .open(if opened.contains(&element) { Some(true) } else { None })
     .show(ui, |ui| {
-        match regions.get_mut(world, element) {
+        match tree.regions.get_mut(world, element) {
             Ok((entity, mut name, mut region)) => {
                 region_ui(queue, ui, region_list, entity, &mut *name, &mut *region);
             },
             Err(_) => {
-                match settlements.get_mut(world, element) {
+                match tree.settlements.get_mut(world, element) {
                     Ok((entity, mut name, mut settlement)) => {
                         settlement_ui(queue, ui, region_list, entity, &mut *name, &mut *settlement);
                     },
@@ -141,10 +167,12 @@ fn recursively_create_ui(
             ui.label("Sub-regions and settlements");
             let children = &subnodes[&element];
             for child in children {
-                recursively_create_ui(*child, &subnodes, queue, ui, world, region_list, regions, settlements);
+                recursively_create_ui(*child, tree, queue, ui, world);
             }
         }
     });
+
+    if opened.contains(&element) { header.header_response.scroll_to_me(Some(egui::Align::Center)); }
 }
 
 fn region_ui(
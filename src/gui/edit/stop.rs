@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+use eframe::egui;
+use crate::world::stop::{StopCondition, StopConditions};
+
+/// Names of things in the world that stop conditions can be about.
+pub(super) struct StopSubjects {
+    pub people: BTreeSet<String>,
+    pub settlements: BTreeSet<String>,
+    pub factions: BTreeSet<String>,
+}
+
+/// Settings for the conditions that end a run early. These can be changed between runs.
+pub(super) fn stop_condition_settings(
+    ui: &mut egui::Ui,
+    conditions: &mut StopConditions,
+    subjects: &StopSubjects,
+) {
+    let mut remove: Option<usize> = None;
+
+    egui::Grid::new("stop_conditions_grid")
+    .spacing([10.0, 3.0])
+    .striped(true)
+    .show(ui, |ui| {
+        for (index, condition) in conditions.0.iter_mut().enumerate() {
+            ui.label("Stop when");
+            match condition {
+                StopCondition::PersonDies(name) => {
+                    name_choice(ui, ("stop_condition_person", index), name, &subjects.people);
+                    ui.label("dies");
+                },
+                StopCondition::PopulationBelow { settlement, population } => {
+                    ui.horizontal(|ui| {
+                        ui.label("the population of");
+                        name_choice(ui, ("stop_condition_settlement", index), settlement, &subjects.settlements);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("drops below");
+                        ui.add(egui::DragValue::new(population)).on_hover_text("This counts the living people in the settlement, not the population it was given.");
+                    });
+                },
+                StopCondition::FactionGone(name) => {
+                    name_choice(ui, ("stop_condition_faction", index), name, &subjects.factions);
+                    ui.label("ceases to exist");
+                },
+                StopCondition::NoLivingPeople => {
+                    ui.label("no living people remain");
+                    ui.label("");
+                },
+            }
+            if ui.button("Remove").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(index) = remove {
+        conditions.0.remove(index);
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Add");
+        let first = |names: &BTreeSet<String>| names.iter().next().cloned().unwrap_or_default();
+        if ui.button("A person dies").clicked() {
+            conditions.0.push(StopCondition::PersonDies(first(&subjects.people)));
+        }
+        if ui.button("A population drops").clicked() {
+            conditions.0.push(StopCondition::PopulationBelow { settlement: first(&subjects.settlements), population: 1 });
+        }
+        if ui.button("A faction ceases to exist").clicked() {
+            conditions.0.push(StopCondition::FactionGone(first(&subjects.factions)));
+        }
+        if ui.button("No living people remain").clicked() {
+            conditions.0.push(StopCondition::NoLivingPeople);
+        }
+    });
+}
+
+/// A name that can be typed in, or picked from the names that exist.
+fn name_choice(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    name: &mut String,
+    names: &BTreeSet<String>,
+) {
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(name).desired_width(120.0));
+        egui::ComboBox::from_id_source(id)
+        .selected_text("")
+        .width(20.0)
+        .show_ui(ui, |ui| {
+            for option in names.iter() {
+                ui.selectable_value(name, option.clone(), option);
+            }
+        });
+    });
+}
@@ -0,0 +1,176 @@
+//! Opening and saving world files.
+
+use std::path::PathBuf;
+use eframe::{egui, epaint::Color32};
+use crate::world::{calendar::Calendar, sim::Simulation, save::{save_world, load_world}};
+use super::{WorldGenApp, AppMemory, modal::ModalWindow, notifs::{Notification, NotificationType}};
+
+const PATH_KEY: &str = "file_dialog_path";
+
+/// The File menu in the top bar. Files can only be opened or saved while the simulation is frozen.
+pub(super) fn file_menu(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+    frozen: bool,
+    has_statistics: bool,
+) {
+    ui.menu_button("File", |ui| {
+        ui.add_enabled_ui(frozen, |ui| {
+            if ui.button("Open...").clicked() {
+                open_path_dialog(memory, "file_dialog_open");
+                ui.close_menu();
+            }
+
+            if ui.button("Save").clicked() {
+                if memory.current_file.is_some() {
+                    memory.markers.insert("save_world_file".to_string());
+                } else {
+                    open_path_dialog(memory, "file_dialog_save");
+                }
+                ui.close_menu();
+            }
+
+            if ui.button("Save as...").clicked() {
+                open_path_dialog(memory, "file_dialog_save");
+                ui.close_menu();
+            }
+        });
+
+        ui.separator();
+
+        ui.add_enabled_ui(frozen && has_statistics, |ui| {
+            if ui.button("Export statistics...").on_hover_text("Saves the statistics collected while simulating as a CSV file, for spreadsheets.").clicked() {
+                let path = match &memory.current_file {
+                    Some(path) => path.with_extension("csv").display().to_string(),
+                    None => "statistics.csv".to_string(),
+                };
+                memory.string_map.insert(PATH_KEY.to_string(), path);
+                memory.markers.insert("file_dialog_export_statistics".to_string());
+                ui.close_menu();
+            }
+        });
+    });
+}
+
+fn open_path_dialog(memory: &mut AppMemory, marker: &str) {
+    let path = match &memory.current_file {
+        Some(path) => path.display().to_string(),
+        None => "world.ron".to_string(),
+    };
+
+    memory.string_map.insert(PATH_KEY.to_string(), path);
+    memory.markers.insert(marker.to_string());
+}
+
+/// Shows a window asking for a file path, if one has been requested.
+pub(super) fn file_dialog(
+    ctx: &egui::Context,
+    memory: &mut AppMemory,
+) {
+    let (title, marker, action) = if memory.markers.contains("file_dialog_open") {
+        ("Open world", "file_dialog_open", "load_world_file")
+    } else if memory.markers.contains("file_dialog_save") {
+        ("Save world as", "file_dialog_save", "save_world_file")
+    } else if memory.markers.contains("file_dialog_export_statistics") {
+        ("Export statistics", "file_dialog_export_statistics", "export_statistics")
+    } else {
+        return;
+    };
+
+    let mut open = true;
+    let mut confirmed = false;
+    egui::Window::new(title)
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(false)
+    .show(ctx, |ui| {
+        let path = memory.string_map.entry(PATH_KEY.to_string()).or_default();
+        ui.horizontal(|ui| {
+            ui.label("Path");
+            ui.add(egui::TextEdit::singleline(path).min_size(eframe::emath::Vec2::new(300.0, 0.0)));
+        });
+
+        ui.add_space(3.0);
+
+        if ui.button("Confirm").clicked() {
+            confirmed = true;
+        }
+    });
+
+    if confirmed {
+        let path = memory.string_map.get(PATH_KEY).cloned().unwrap_or_default();
+        memory.markers.remove(marker);
+        memory.pending_file = Some(PathBuf::from(path));
+        memory.markers.insert(action.to_string());
+    } else if !open {
+        memory.markers.remove(marker);
+    }
+}
+
+/// Deals with reading and writing files from the UI
+pub(super) fn file_fns(
+    app: &mut WorldGenApp
+) {
+    // Load a world, replacing the current one
+    if app.memory.markers.contains("load_world_file") {
+        app.memory.markers.remove("load_world_file");
+        let Some(path) = app.memory.pending_file.take() else { return };
+
+        match load_world(&path) {
+            Ok(world) => {
+                app.simulation = Simulation::new(world);
+                app.memory.notifications.push(Notification::new(format!("Opened {}", path.display()), 5.0, NotificationType::Info));
+                app.memory.current_file = Some(path);
+            },
+            Err(error) => {
+                let errorstr = format!("Couldn't open {}: {:?}", path.display(), error);
+                app.memory.modal_popup = Some(ModalWindow::new(&errorstr).outline_color(Color32::LIGHT_RED));
+            },
+        }
+    }
+
+    // Save the world, either to a new path or the current one
+    if app.memory.markers.contains("save_world_file") {
+        app.memory.markers.remove("save_world_file");
+        let Some(path) = app.memory.pending_file.take().or(app.memory.current_file.clone()) else { return };
+
+        let result = match app.simulation.world() {
+            Ok(world) => save_world(world, &path),
+            Err(error) => {
+                app.memory.notifications.push(Notification::new(format!("Couldn't save while the simulation is running: {:?}", error), 10.0, NotificationType::Warning));
+                return;
+            },
+        };
+
+        match result {
+            Ok(()) => {
+                app.memory.notifications.push(Notification::new(format!("Saved to {}", path.display()), 5.0, NotificationType::Info));
+                app.memory.current_file = Some(path);
+            },
+            Err(error) => {
+                let errorstr = format!("Couldn't save to {}: {:?}", path.display(), error);
+                app.memory.modal_popup = Some(ModalWindow::new(&errorstr).outline_color(Color32::LIGHT_RED));
+            },
+        }
+    }
+
+    // Write the statistics from previous runs as CSV
+    if app.memory.markers.remove("export_statistics") {
+        let Some(path) = app.memory.pending_file.take() else { return };
+
+        let calendar = match app.simulation.world() {
+            Ok(world) => world.get_resource::<Calendar>().cloned().unwrap_or_default(),
+            Err(_) => Calendar::default(),
+        };
+
+        match app.simulation.statistics().write_csv(&path, &calendar) {
+            Ok(()) => {
+                app.memory.notifications.push(Notification::new(format!("Exported statistics to {}", path.display()), 5.0, NotificationType::Info));
+            },
+            Err(error) => {
+                let errorstr = format!("Couldn't export statistics to {}: {:?}", path.display(), error);
+                app.memory.modal_popup = Some(ModalWindow::new(&errorstr).outline_color(Color32::LIGHT_RED));
+            },
+        }
+    }
+}
@@ -4,24 +4,27 @@ mod ecs;
 mod notifs;
 mod modal;
 mod sim;
+mod file;
+mod diagnostics;
+mod branches;
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Duration;
 use bevy::ecs::system::CommandQueue;
 use bevy::ecs::prelude::Entity;
 use eframe::{egui, Frame, App};
 use either::Either::{Right, Left};
-use crate::world::defs::{SimulationConfig, Timespan, HistoryDirection};
-use crate::world::presets::bck_day::add_backward_day_presets;
-use crate::world::presets::bck_mon::add_backward_month_presets;
-use crate::world::presets::fwd_day::add_forward_day_presets;
-use crate::world::presets::fwd_mon::add_forward_month_presets;
-use crate::world::sim::{Simulation, validate_world, SimulationData};
+use crate::world::{sim::{Simulation, SimulationFailure}, time::format_duration, diagnostics::Diagnostic, branch::{BranchSummary, DEFAULT_BRANCH_NAME}, stats::StatisticsConfig};
 
 use self::modal::ModalWindow;
 use self::notifs::{Notification, show_notifications, update_notifications};
-use self::sim::simulation_fns;
+use self::sim::{simulation_fns, simulation_menu, failure_window, checkpoints_window};
+use self::file::{file_menu, file_dialog, file_fns};
+use self::diagnostics::diagnostics_window;
+use self::branches::{branch_fns, comparison_window};
 use self::view::view_ui;
-use self::edit::edit_ui;
+use self::edit::{edit_ui, HistoryFilter, FamilyTreeView};
 
 pub struct WorldGenApp {
     simulation: Simulation,
@@ -34,6 +37,32 @@ struct AppMemory {
     string_map: BTreeMap<String, String>,
     modal_popup: Option<ModalWindow>,
     notifications: Vec<Notification>,
+    /// The file the world was last opened from or saved to.
+    current_file: Option<PathBuf>,
+    /// A path chosen in a file dialog, waiting to be used.
+    pending_file: Option<PathBuf>,
+    /// An entity whose editor entry should be opened and scrolled to.
+    focus: Option<Entity>,
+    history_filter: HistoryFilter,
+    /// The family tree window, if it's open.
+    family_tree: Option<FamilyTreeView>,
+    /// Problems found the last time the world was checked.
+    diagnostics: Vec<Diagnostic>,
+    /// Why the simulation last stopped, if it failed and the user hasn't seen it yet.
+    failure: Option<SimulationFailure>,
+    /// Names of every branch, copied from the simulation each frame.
+    branch_names: Vec<String>,
+    current_branch: String,
+    /// Branch summaries, if the comparison window is open.
+    branch_comparison: Option<Vec<BranchSummary>>,
+    /// The most ticks to run each second. Zero means no limit.
+    speed_limit: f32,
+    /// How many ticks to advance by when stepping a paused simulation.
+    step_count: u32,
+    /// Which statistics to collect while the simulation is executing.
+    statistics: StatisticsConfig,
+    /// How long each tick took on average in the last run, copied from the simulation each frame.
+    tick_time: Option<Duration>,
 }
 
 impl Default for AppMemory {
@@ -43,6 +72,20 @@ impl Default for AppMemory {
             string_map: BTreeMap::new(),
             modal_popup: None,
             notifications: vec![],
+            current_file: None,
+            pending_file: None,
+            focus: None,
+            history_filter: HistoryFilter::default(),
+            family_tree: None,
+            diagnostics: vec![],
+            failure: None,
+            branch_names: vec![],
+            current_branch: DEFAULT_BRANCH_NAME.to_string(),
+            branch_comparison: None,
+            speed_limit: 0.0,
+            step_count: 1,
+            statistics: StatisticsConfig::default(),
+            tick_time: None,
         }
     }
 }
@@ -61,50 +104,80 @@ impl App for WorldGenApp {
         update_notifications(&mut self.memory.notifications, ctx);
         show_notifications(&self.memory.notifications, ctx);
 
+        // Menu bar
+        let frozen = self.simulation.world().is_ok();
+        self.memory.tick_time = self.simulation.tick_time();
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                file_menu(ui, &mut self.memory, frozen, !self.simulation.statistics().is_empty());
+                simulation_menu(ui, &mut self.memory, frozen, self.simulation.has_pre_run_snapshot());
+            });
+        });
+
         // Show a different UI based on the simulation state
         match self.simulation.current() {
-            Ok(value) => {
-                match value {
-                    // The simulation is frozen and can be edited
-                    Left(data) => {
-                        egui::CentralPanel::default()
-                        .show(ctx, |ui| {
-                            let mut queue = CommandQueue::default();
-                            edit_ui(ui, &mut self.memory, &mut queue, data);
-                            queue.apply(&mut data.app.world);
-                        });
-                    },
-                    // The simulation is running and the boundary can be read
-                    Right(boundary) => {
-                        // Always repaint the UI while the simulation is in progress
-                        if boundary.steps_complete != boundary.steps_total { ctx.request_repaint(); }
-
-                        if boundary.simulation_exited { self.memory.markers.insert("try_freeze_simulation".to_string()); }
-
-                        egui::TopBottomPanel::top("sim_status_panel")
-                        .show_separator_line(false)
-                        .show(ctx, |ui| {
-                            ui.add_space(2.0);
-                            ui.horizontal(|ui| {
-                                if ui.button("Stop simulation").clicked() {
-                                    self.memory.markers.insert("try_freeze_simulation".to_owned());
-                                }
-                        
-                                let percent = boundary.steps_complete as f32 / boundary.steps_total as f32;
-                                ui.add(egui::ProgressBar::new(percent).show_percentage());
-                            });
-                            ui.add_space(1.0);
-                        });
-
-                        egui::CentralPanel::default().show(ctx, |ui| {
-                            view_ui(ui, &mut self.memory, boundary);
-                        });
-                    },
-                }
+            // The simulation is frozen and can be edited
+            Left(data) => {
+                egui::CentralPanel::default()
+                .show(ctx, |ui| {
+                    let mut queue = CommandQueue::default();
+                    edit_ui(ui, &mut self.memory, &mut queue, data);
+                    queue.apply(&mut data.app.world);
+                });
             },
-            // The simulation boundary is poisoned
-            Err(_) => {
-                self.memory.markers.insert("try_freeze_simulation".to_owned());
+            // The simulation is running, and this is what's been heard from it
+            Right(boundary) => {
+                // Always repaint the UI while the simulation is in progress
+                if boundary.steps_complete != boundary.steps_total { ctx.request_repaint(); }
+
+                if boundary.simulation_exited { self.memory.markers.insert("try_freeze_simulation".to_string()); }
+
+                egui::TopBottomPanel::top("sim_status_panel")
+                .show_separator_line(false)
+                .show(ctx, |ui| {
+                    ui.add_space(2.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Stop simulation").clicked() {
+                            self.memory.markers.insert("try_freeze_simulation".to_owned());
+                        }
+
+                        if boundary.paused {
+                            if ui.button("Resume").clicked() {
+                                self.memory.markers.insert("resume_simulation".to_owned());
+                            }
+                            if ui.button("Step").on_hover_text("Runs this many steps, then pauses again.").clicked() {
+                                self.memory.markers.insert("advance_simulation".to_owned());
+                            }
+                            ui.add(egui::DragValue::new(&mut self.memory.step_count).clamp_range(1..=1000));
+                        } else if ui.button("Pause").clicked() {
+                            self.memory.markers.insert("pause_simulation".to_owned());
+                        }
+
+                        ui.label("Speed limit");
+                        let speed = ui.add(egui::DragValue::new(&mut self.memory.speed_limit).clamp_range(0.0..=1000.0).speed(0.5).suffix(" steps/s"))
+                            .on_hover_text("The most steps to run each second. Set this to zero for no limit.");
+                        if speed.changed() {
+                            self.memory.markers.insert("set_speed_limit".to_owned());
+                        }
+
+                        // The progress bar fills the rest of the row, so this goes first
+                        if let Some(remaining) = boundary.time_remaining() {
+                            ui.label(format!("About {} left", format_duration(remaining)));
+                        }
+
+                        let percent = boundary.steps_complete as f32 / boundary.steps_total as f32;
+                        ui.add(egui::ProgressBar::new(percent).show_percentage());
+
+                        if let Some(error) = &boundary.error {
+                            ui.colored_label(ui.visuals().error_fg_color, error);
+                        }
+                    });
+                    ui.add_space(1.0);
+                });
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    view_ui(ui, &mut self.memory, boundary);
+                });
             },
         }
         
@@ -118,31 +191,16 @@ impl App for WorldGenApp {
             self.memory.modal_popup = None;
         }
 
-        simulation_fns(self);
-    }
-}
+        file_dialog(ctx, &mut self.memory);
+        diagnostics_window(ctx, &mut self.memory);
+        failure_window(ctx, &mut self.memory, self.simulation.has_pre_run_snapshot());
+        checkpoints_window(ctx, &mut self.memory, &self.simulation, frozen);
+        comparison_window(ctx, &mut self.memory, frozen);
 
-// Adds the necessary systems for simulation
-fn systems_check(
-    simulation: &mut SimulationData,
-) {
-    // Get direction and timestep
-    let mut cfg = simulation.app.world.resource_mut::<SimulationConfig>();
-    if cfg.locked_in { return; } // Preset is already set
-    let (direction, timestep) = (cfg.direction.clone(), cfg.timespan.clone());
-    cfg.locked_in = true;
-    drop(cfg);
-
-    // Apply systems to app
-    match (direction, timestep) {
-        (HistoryDirection::Forwards, Timespan::Months) => add_forward_month_presets(&mut simulation.app),
-        (HistoryDirection::Forwards, Timespan::Days) => add_forward_day_presets(&mut simulation.app),
-        (HistoryDirection::Backwards, Timespan::Months) => add_backward_month_presets(&mut simulation.app),
-        (HistoryDirection::Backwards, Timespan::Days) => add_backward_day_presets(&mut simulation.app),
+        simulation_fns(self);
+        file_fns(self);
+        branch_fns(self);
     }
-
-    // Validate world to make sure everything is in order
-    validate_world(&mut simulation.app.world);
 }
 
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord)]
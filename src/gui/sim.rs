@@ -1,7 +1,53 @@
-use eframe::epaint::Color32;
+use eframe::{egui, epaint::Color32, emath::Align2};
 use replace_with::replace_with_or_abort;
-use crate::world::sim::Simulation;
-use super::{WorldGenApp, systems_check, modal::ModalWindow, notifs::{Notification, NotificationType}};
+use crate::world::{sim::{Simulation, SimulationError, Recovered, systems_check, validate_world}, diagnostics::has_errors};
+use super::{WorldGenApp, AppMemory, modal::ModalWindow, notifs::{Notification, NotificationType}};
+
+/// Marker for saving pre-run snapshots to disk.
+pub(super) const SNAPSHOT_TO_DISK_KEY: &str = "pre_run_snapshot_to_disk";
+/// Where pre-run snapshots are saved to.
+pub(super) const SNAPSHOT_PATH_KEY: &str = "pre_run_snapshot_path";
+
+/// Marker for the checkpoints window being open.
+const CHECKPOINTS_WINDOW_KEY: &str = "checkpoints_window_open";
+/// The tick of the checkpoint to restore.
+const CHECKPOINT_TICK_KEY: &str = "restore_checkpoint_tick";
+
+/// The Simulation menu in the top bar.
+pub(super) fn simulation_menu(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+    frozen: bool,
+    has_snapshot: bool,
+) {
+    ui.menu_button("Simulation", |ui| {
+        ui.add_enabled_ui(frozen && has_snapshot, |ui| {
+            if ui.button("Revert to pre-simulation state").on_hover_text(
+                "Replaces the world with how it was before it was first simulated, even if it's been stopped and resumed since. Any changes made since then are lost."
+            ).clicked() {
+                memory.markers.insert("restore_pre_run".to_string());
+                ui.close_menu();
+            }
+        });
+
+        if ui.button("Checkpoints...").clicked() {
+            memory.markers.insert(CHECKPOINTS_WINDOW_KEY.to_string());
+            ui.close_menu();
+        }
+
+        ui.separator();
+
+        let mut to_disk = memory.markers.contains(SNAPSHOT_TO_DISK_KEY);
+        if ui.checkbox(&mut to_disk, "Save pre-run snapshots to disk").changed() {
+            if to_disk { memory.markers.insert(SNAPSHOT_TO_DISK_KEY.to_string()); } else { memory.markers.remove(SNAPSHOT_TO_DISK_KEY); }
+        }
+
+        ui.add_enabled_ui(to_disk, |ui| {
+            let path = memory.string_map.entry(SNAPSHOT_PATH_KEY.to_string()).or_insert("pre-run.ron".to_string());
+            egui::TextEdit::singleline(path).hint_text("Snapshot file path").show(ui);
+        });
+    });
+}
 
 /// Deals with changing the simulation from the UI
 pub(super) fn simulation_fns(
@@ -11,32 +57,225 @@ pub(super) fn simulation_fns(
     if app.memory.markers.contains("try_execute_simulation") {
         app.memory.markers.remove("try_execute_simulation");
 
-        match app.simulation.current_or_err() {
+        let acknowledged = app.memory.markers.remove("diagnostics_acknowledged");
+
+        let start = match app.simulation.current_or_err() {
             Ok(simulation) => {
-                systems_check(simulation);
+                // Don't start if there are errors, or warnings that haven't been seen yet
+                let diagnostics = validate_world(&mut simulation.app.world);
+                if has_errors(&diagnostics) || (!diagnostics.is_empty() && !acknowledged) {
+                    app.memory.diagnostics = diagnostics;
+                    false
+                } else {
+                    systems_check(simulation);
+                    true
+                }
             },
             Err(_error) => todo!(),
+        };
+
+        if start {
+            let _ = app.simulation.configure_statistics(app.memory.statistics.clone());
+            replace_with_or_abort(&mut app.simulation, |sim| sim.try_execute().0);
+            app.memory.markers.insert("set_speed_limit".to_string());
+
+            // Optionally keep a copy of the starting point on disk too
+            if app.memory.markers.contains(SNAPSHOT_TO_DISK_KEY) {
+                let path = app.memory.string_map.get(SNAPSHOT_PATH_KEY).cloned().unwrap_or_default();
+                if let Some(snapshot) = app.simulation.pre_run_snapshot() {
+                    if let Err(error) = snapshot.write(&path) {
+                        app.memory.notifications.push(Notification::new(format!("Couldn't save the pre-run snapshot to {}: {:?}", path, error), 10.0, NotificationType::Error));
+                    }
+                }
+            }
         }
+    }
+
+    // Controls for a running simulation
+    if app.memory.markers.remove("pause_simulation") {
+        let _ = app.simulation.pause();
+    }
+
+    if app.memory.markers.remove("resume_simulation") {
+        let _ = app.simulation.resume();
+    }
+
+    if app.memory.markers.remove("advance_simulation") {
+        let _ = app.simulation.advance(app.memory.step_count);
+    }
+
+    if app.memory.markers.remove("set_speed_limit") {
+        let limit = if app.memory.speed_limit > 0.0 { Some(app.memory.speed_limit) } else { None };
+        let _ = app.simulation.set_speed_limit(limit);
+    }
+
+    if app.memory.markers.remove("configure_statistics") {
+        let _ = app.simulation.configure_statistics(app.memory.statistics.clone());
+    }
+
+    if app.memory.markers.remove("request_snapshot") {
+        let _ = app.simulation.request_snapshot();
+    }
+
+    // Check the world without starting
+    if app.memory.markers.contains("validate_world") {
+        app.memory.markers.remove("validate_world");
 
-        replace_with_or_abort(&mut app.simulation, |sim| sim.try_execute().0);
+        if let Ok(simulation) = app.simulation.current_or_err() {
+            app.memory.diagnostics = validate_world(&mut simulation.app.world);
+            if app.memory.diagnostics.is_empty() {
+                app.memory.notifications.push(Notification::new("No problems found.", 5.0, NotificationType::Info));
+            }
+        }
     }
 
     // Freeze simulation
     if app.memory.markers.contains("try_freeze_simulation") {
         app.memory.markers.remove("try_freeze_simulation");
         replace_with_or_abort(&mut app.simulation, |sim| {
-            match sim.freeze() {
-                Ok(success) => {
-                    app.memory.modal_popup = Some(ModalWindow::new("The simulation successfully exited.").outline_color(Color32::LIGHT_GREEN));
-                    success
+            let (sim, result) = sim.freeze();
+            match result {
+                Ok(()) => {
+                    let message = match sim.stopped_by() {
+                        Some(condition) => format!("The simulation stopped early: {}.", condition),
+                        None => "The simulation successfully exited.".to_string(),
+                    };
+                    app.memory.modal_popup = Some(ModalWindow::new(message).outline_color(Color32::LIGHT_GREEN));
                 },
-                Err(error) => {
-                    let errorstr = format!("Simulation encountered an error: {:?}", error);
-                    app.memory.modal_popup = Some(ModalWindow::new(&errorstr).outline_color(Color32::LIGHT_RED));
+                Err(failure) => {
+                    let errorstr = format!("Simulation encountered an error: {:?}", failure.error);
                     app.memory.notifications.push(Notification::new(&errorstr, 20.0, NotificationType::Error));
-                    Simulation::default() // create new default sim
+                    app.memory.failure = Some(failure);
                 },
             }
+            sim
         });
     }
-}
\ No newline at end of file
+
+    // Go back to a checkpoint
+    if app.memory.markers.contains("restore_checkpoint") {
+        app.memory.markers.remove("restore_checkpoint");
+
+        let tick = app.memory.string_map.remove(CHECKPOINT_TICK_KEY).and_then(|tick| tick.parse::<u32>().ok());
+        let result = match tick {
+            Some(tick) => app.simulation.restore_checkpoint(tick),
+            None => Err(SimulationError::NoSnapshot),
+        };
+
+        match result {
+            Ok(()) => app.memory.notifications.push(Notification::new("Restored the world from the checkpoint.", 5.0, NotificationType::Info)),
+            Err(error) => app.memory.notifications.push(Notification::new(format!("Couldn't restore the checkpoint: {:?}", error), 10.0, NotificationType::Error)),
+        }
+    }
+
+    // Go back to how the world was before the simulation started
+    if app.memory.markers.contains("restore_pre_run") {
+        app.memory.markers.remove("restore_pre_run");
+
+        match app.simulation.restore_pre_run() {
+            Ok(()) => app.memory.notifications.push(Notification::new("Restored the world from before the simulation.", 5.0, NotificationType::Info)),
+            Err(error) => app.memory.notifications.push(Notification::new(format!("Couldn't restore the world: {:?}", error), 10.0, NotificationType::Error)),
+        }
+    }
+}
+
+/// Shows what happened when the simulation failed, and lets the user pick which world to keep.
+pub(super) fn failure_window(
+    ctx: &egui::Context,
+    memory: &mut AppMemory,
+    has_snapshot: bool,
+) {
+    let Some(failure) = &memory.failure else { return; };
+
+    let mut close = false;
+
+    egui::Window::new("The simulation failed")
+    .anchor(Align2::CENTER_CENTER, [0.0; 2])
+    .collapsible(false)
+    .resizable(false)
+    .show(ctx, |ui| {
+        let error = match &failure.error {
+            SimulationError::TickPanicked { tick, message } => format!("Something went wrong during step {}: {}", tick + 1, message),
+            other => format!("{:?}", other),
+        };
+        ui.label(egui::RichText::new(error).color(Color32::LIGHT_RED));
+
+        ui.add_space(4.0);
+
+        ui.label(match failure.recovered {
+            Recovered::LastGoodState(_) => "The world has been restored to how it was just before that step.".to_string(),
+            Recovered::Checkpoint(tick) => format!("The world has been restored to the checkpoint from step {}. Anything simulated after it was lost.", tick),
+            Recovered::PreRunSnapshot => "The world has been restored to how it was before the simulation started. Everything simulated was lost.".to_string(),
+            Recovered::Nothing => "The world couldn't be recovered.".to_string(),
+        });
+
+        ui.add_space(4.0);
+
+        ui.horizontal(|ui| {
+            match failure.recovered {
+                Recovered::LastGoodState(_) | Recovered::Checkpoint(_) => {
+                    if ui.button("Keep").clicked() { close = true; }
+                    if has_snapshot && ui.button("Restore pre-run snapshot").clicked() {
+                        memory.markers.insert("restore_pre_run".to_string());
+                        close = true;
+                    }
+                },
+                Recovered::PreRunSnapshot | Recovered::Nothing => {
+                    if ui.button("Ok").clicked() { close = true; }
+                },
+            }
+        });
+    });
+
+    if close { memory.failure = None; }
+}
+
+/// Lists the checkpoints taken in previous runs, which can be restored while the simulation is frozen.
+pub(super) fn checkpoints_window(
+    ctx: &egui::Context,
+    memory: &mut AppMemory,
+    simulation: &Simulation,
+    frozen: bool,
+) {
+    if !memory.markers.contains(CHECKPOINTS_WINDOW_KEY) { return; }
+
+    let mut open = true;
+
+    egui::Window::new("Checkpoints")
+    .open(&mut open)
+    .default_size([300.0, 300.0])
+    .show(ctx, |ui| {
+        let checkpoints = simulation.checkpoints();
+        if checkpoints.is_empty() {
+            ui.label("No checkpoints have been taken yet.");
+            return;
+        }
+
+        if !frozen { ui.label("Checkpoints from this run appear once it's stopped."); }
+
+        egui::ScrollArea::vertical()
+        .id_source("checkpoints_scroll")
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            egui::Grid::new("checkpoints_grid")
+            .striped(true)
+            .spacing([10.0, 3.0])
+            .show(ui, |ui| {
+                // Newest first, since those are usually what's wanted
+                for checkpoint in checkpoints.iter().rev() {
+                    ui.label(format!("{}", checkpoint.date));
+                    ui.label(format!("Step {}", checkpoint.tick));
+                    ui.add_enabled_ui(frozen, |ui| {
+                        if ui.button("Restore").on_hover_text("Replaces the current world with this checkpoint.").clicked() {
+                            memory.string_map.insert(CHECKPOINT_TICK_KEY.to_string(), checkpoint.tick.to_string());
+                            memory.markers.insert("restore_checkpoint".to_string());
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        });
+    });
+
+    if !open { memory.markers.remove(CHECKPOINTS_WINDOW_KEY); }
+}
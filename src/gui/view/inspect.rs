@@ -0,0 +1,112 @@
+use eframe::egui;
+use crate::{world::{calendar::Calendar, living::Living, protocol::SimulationBoundary, snapshot::{WorldSnapshot, PersonSnapshot, PlaceSnapshot}}, gui::AppMemory};
+
+const TAB_KEY: &str = "view_inspect_tab";
+const SEARCH_KEY: &str = "view_inspect_search";
+
+/// Lists the people and places in the most recent snapshot, with a search box.
+pub(super) fn inspect_ui(
+    ui: &mut egui::Ui,
+    memory: &mut AppMemory,
+    snapshot: Option<&WorldSnapshot>,
+    calendar: &Calendar,
+) {
+    let Some(snapshot) = snapshot else {
+        ui.label("Waiting for the simulation to publish a snapshot...");
+        return;
+    };
+
+    ui.label(format!("As of {} (step {})", calendar.format_after(snapshot.date), snapshot.tick));
+
+    // Tabs and search
+    let current_tab = memory.string_map.entry(TAB_KEY.to_string()).or_insert("People".to_string());
+    ui.horizontal(|ui| {
+        ui.selectable_value(current_tab, "People".to_owned(), "People");
+        ui.selectable_value(current_tab, "Places".to_owned(), "Places");
+    });
+    let people_tab = current_tab == "People";
+
+    let search = memory.string_map.entry(SEARCH_KEY.to_string()).or_default();
+    ui.add_sized([ui.available_width(), 0.0], egui::TextEdit::singleline(search).hint_text("Enter a search term..."));
+    let search_term = search.to_lowercase();
+
+    ui.separator();
+
+    let row_height = ui.spacing().interact_size.y;
+
+    if people_tab {
+        let people: Vec<&PersonSnapshot> = snapshot.people.iter().filter(|person| matches_person(person, &search_term)).collect();
+        ui.label(format!("Showing {} of {} people", people.len(), snapshot.people.len()));
+
+        egui::ScrollArea::both()
+        .id_source("inspect_people_scroll_area")
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, people.len(), |ui, range| {
+            for person in &people[range] {
+                ui.horizontal(|ui| {
+                    let name = egui::RichText::new(&person.name).strong();
+                    ui.label(match person.living {
+                        Living::Alive => name,
+                        Living::Dead => name.weak().strikethrough(),
+                    }).on_hover_text(format!("{:?}", person.entity));
+                    ui.label(format!("{}", person.age));
+                    if let Some(species) = &person.species { ui.label(species); }
+                    if let Some(place) = &person.place { ui.label(format!("in {}", place)); }
+                    if let Some(health) = person.health { ui.label(format!("health {:.2}", health)); }
+                    if !person.afflictions.is_empty() { ui.label(person.afflictions.join(", ")); }
+                });
+            }
+        });
+    } else {
+        let places: Vec<&PlaceSnapshot> = snapshot.places.iter().filter(|place| matches_place(place, &search_term)).collect();
+        ui.label(format!("Showing {} of {} places", places.len(), snapshot.places.len()));
+
+        egui::ScrollArea::both()
+        .id_source("inspect_places_scroll_area")
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, places.len(), |ui, range| {
+            for place in &places[range] {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(&place.name).strong()).on_hover_text(format!("{:?}", place.entity));
+                    ui.label(if place.settlement { "Settlement" } else { "Region" });
+                    if let Some(parent) = &place.parent { ui.label(format!("in {}", parent)); }
+                    if let Some(population) = place.population { ui.label(format!("population {}", population)); }
+                    ui.label(format!("{} living people", place.residents));
+                });
+            }
+        });
+    }
+}
+
+fn matches_person(person: &PersonSnapshot, search_term: &str) -> bool {
+    if search_term.is_empty() { return true; }
+    [Some(&person.name), person.species.as_ref(), person.place.as_ref()].into_iter().flatten()
+        .chain(person.afflictions.iter())
+        .any(|text| text.to_lowercase().contains(search_term))
+}
+
+fn matches_place(place: &PlaceSnapshot, search_term: &str) -> bool {
+    if search_term.is_empty() { return true; }
+    [Some(&place.name), place.parent.as_ref()].into_iter().flatten()
+        .any(|text| text.to_lowercase().contains(search_term))
+}
+
+/// Lists the history recorded most recently, newest first.
+pub(super) fn recent_events_ui(
+    ui: &mut egui::Ui,
+    sim: &SimulationBoundary,
+) {
+    egui::ScrollArea::vertical()
+    .id_source("recent_events_scroll_area")
+    .auto_shrink([false, false])
+    .show(ui, |ui| {
+        if sim.recent_events.iter().len() == 0 { ui.label("Nothing has happened yet."); }
+        for event in sim.recent_events.iter().rev() {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(sim.calendar.format_after(event.date)).monospace());
+                ui.label(egui::RichText::new(event.kind.name()).strong());
+                ui.label(&event.summary);
+            });
+        }
+    });
+}
@@ -1,37 +1,57 @@
 mod plots;
+mod inspect;
 
-use std::sync::RwLockReadGuard;
 use eframe::{egui::{self, Frame, Style}, epaint::Shadow};
-use crate::world::sim::SimulationBoundary;
-use self::plots::{entity_count_plot, tick_time_plot};
+use crate::world::{protocol::SimulationBoundary, stats::PROBES};
+use self::{plots::{statistics_settings, statistic_plot}, inspect::{inspect_ui, recent_events_ui}};
 
 use super::AppMemory;
 
 pub(super) fn view_ui(
     ui: &mut egui::Ui,
-    _memory: &mut AppMemory,
-    sim: RwLockReadGuard<SimulationBoundary>,
+    memory: &mut AppMemory,
+    sim_ref: &SimulationBoundary,
 ) {
-    let sim_ref = &*sim;
     let ctx = ui.ctx();
 
     let dframe = Frame::window(&Style::default()).shadow(Shadow::NONE);
 
     // Start plots
-    egui::Window::new("Tick time")
-    .default_size([400.0, 120.0])
+    egui::Window::new("Statistics")
     .default_open(false)
     .frame(dframe)
     .show(ctx, |ui| {
-        tick_time_plot(ui, sim_ref);
+        if statistics_settings(ui, &mut memory.statistics) {
+            memory.markers.insert("configure_statistics".to_owned());
+        }
     });
 
-    egui::Window::new("Entity count")
-    .default_size([400.0, 120.0])
+    for probe in PROBES.iter().filter(|probe| memory.statistics.enabled.contains(probe.name)) {
+        egui::Window::new(probe.name)
+        .default_size([400.0, 120.0])
+        .default_open(false)
+        .frame(dframe)
+        .show(ctx, |ui| {
+            statistic_plot(ui, sim_ref, probe);
+        });
+    }
+    // End plots
+
+    egui::Window::new("World")
+    .default_size([500.0, 400.0])
     .default_open(false)
     .frame(dframe)
     .show(ctx, |ui| {
-        entity_count_plot(ui, sim_ref);
+        // Only ask for snapshots while they're being looked at
+        memory.markers.insert("request_snapshot".to_owned());
+        inspect_ui(ui, memory, sim_ref.snapshot.as_ref(), &sim_ref.calendar);
+    });
+
+    egui::Window::new("Recent events")
+    .default_size([500.0, 250.0])
+    .default_open(false)
+    .frame(dframe)
+    .show(ctx, |ui| {
+        recent_events_ui(ui, sim_ref);
     });
-    // End plots
 }
\ No newline at end of file
@@ -1,66 +1,59 @@
 use eframe::egui::{self, plot::{Plot, PlotPoints, Line, Legend, Corner}};
-use crate::world::sim::{SimulationBoundary, RECORD_LENGTH};
+use crate::world::{protocol::SimulationBoundary, stats::{Probe, PROBES, StatisticsConfig}, time::Age};
 
-pub(super) fn tick_time_plot(
+/// Lets the user choose which statistics are collected, and how often.
+pub(super) fn statistics_settings(
     ui: &mut egui::Ui,
-    sim: &SimulationBoundary,
-) {
-    // Tick time display
-    Plot::new("tick_time_plot")
-    .allow_drag(false)
-    .allow_scroll(false)
-    .allow_zoom(false)
-    .allow_boxed_zoom(false)
-    .include_x(0.0)
-    .include_x(RECORD_LENGTH as f64)
-    .include_y(0.0)
-    .include_y(0.005)
-    .auto_bounds_y()
-    .show_x(false)
-    .label_formatter(|name, value| {
-        if value.y < 1.0 {
-            format!("{}\n{:.3} milliseconds", name, (value.y * 1000.0))
-        } else {
-            format!("{}\n{:.4} seconds", name, value.y)
-        }
-    })
-    .x_axis_formatter(|_, _| format!(""))
-    .show(ui, |plot_ui| {
-        let points: PlotPoints = sim.tick_time_history.iter().enumerate().map(|(i, val)| { [i as f64, *val] }).collect();
-        let line = Line::new(points);
-        plot_ui.line(line);
+    config: &mut StatisticsConfig,
+) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Sample every");
+        changed |= ui.add(egui::DragValue::new(&mut config.interval).clamp_range(1..=u32::MAX).suffix(" steps")).changed();
     });
+
+    for probe in PROBES {
+        let mut enabled = config.enabled.contains(probe.name);
+        if ui.checkbox(&mut enabled, probe.name).on_hover_text(probe.description).changed() {
+            if enabled { config.enabled.insert(probe.name); } else { config.enabled.remove(probe.name); }
+            changed = true;
+        }
+    }
+
+    changed
 }
 
-pub(super) fn entity_count_plot(
+/// Plots every series collected for a probe this run.
+pub(super) fn statistic_plot(
     ui: &mut egui::Ui,
     sim: &SimulationBoundary,
+    probe: &Probe,
 ) {
-    // Stats that will be shown in the graph
-    let stats = [
-        ("Total", &sim.entity_count_history),
-        ("People", &sim.people_count_history),
-        ("Places", &sim.place_count_history),
-    ];
+    let Some(series) = sim.statistics.series(probe.name) else {
+        ui.label("Nothing has been collected yet.");
+        return;
+    };
+
+    let format = probe.format;
+    let calendar = sim.calendar.clone();
+    let year_length = calendar.year_length().max(1) as f64;
 
-    // Plot
-    Plot::new("entity_count_plot")
+    Plot::new(format!("{}_plot", probe.name))
     .allow_drag(false)
     .allow_scroll(false)
     .allow_zoom(false)
     .allow_boxed_zoom(false)
-    .include_x(0.0)
-    .include_x(RECORD_LENGTH as f64)
-    .show_x(false)
+    .include_y(0.0)
     .legend(Legend::default().position(Corner::LeftTop))
-    .label_formatter(|name, value| format!("{}\n{}", name, value.y.floor()))
+    .label_formatter(move |name, value| format!("{}\n{}\n{}", name, calendar.format_after(Age::from_days((value.x * year_length) as u32)), format(value.y)))
+    .x_axis_formatter(|years, _| format!("{} years", years))
     .y_axis_formatter(|_, _| format!(""))
-    .x_axis_formatter(|_, _| format!(""))
     .show(ui, |plot_ui| {
-        for (name, stat) in stats {
-            let points: PlotPoints = stat.iter().enumerate().map(|(i, val)| [i as f64, *val]).collect();
+        for (name, values) in series.iter() {
+            let points: PlotPoints = sim.statistics.points(values, &sim.calendar).into();
             let line = Line::new(points).name(name);
             plot_ui.line(line);
         }
     });
-}
\ No newline at end of file
+}
@@ -1,3 +1,4 @@
+pub mod cli;
 pub mod gui;
 pub mod world;
 
@@ -5,6 +6,16 @@ use eframe::egui;
 use gui::WorldGenApp;
 
 fn main() -> Result<(), eframe::Error> {
+    // Run without a window if a command was given
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if cli::is_cli(&args) {
+        if let Err(error) = cli::run_cli(&args) {
+            cli::report_error(&error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let options = eframe::NativeOptions {
         initial_window_size: Some(egui::vec2(600.0, 400.0)),
         ..Default::default()
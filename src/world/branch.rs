@@ -0,0 +1,67 @@
+//! Alternate histories of the same world.
+
+use std::time::Duration;
+use super::{event::HistoryEventKind, living::Living, save::WorldFile, sim::Checkpoint, stats::Statistics, stop::StopCondition, time::Age};
+
+/// The name of the branch a new simulation starts on.
+pub const DEFAULT_BRANCH_NAME: &str = "main";
+
+/// A branch that isn't the one currently being worked on.
+pub(super) struct Branch {
+    pub name: String,
+    /// The world as it was when this branch was switched away from.
+    pub world: WorldFile,
+    /// The branch's own pre-run snapshot.
+    pub pre_run: Option<WorldFile>,
+    /// The branch's own checkpoints.
+    pub checkpoints: Vec<Checkpoint>,
+    /// Statistics collected on the branch.
+    pub statistics: Statistics,
+    /// How long each tick took on average in the branch's last run.
+    pub tick_time: Option<Duration>,
+    /// The condition that ended the branch's last run early, if one did.
+    pub stopped_by: Option<StopCondition>,
+}
+
+/// Numbers about a branch, for comparing it to others.
+#[derive(Debug, Clone)]
+pub struct BranchSummary {
+    pub name: String,
+    /// How much in-world time has been simulated.
+    pub time_elapsed: Age,
+    pub seed: u32,
+    /// People who are still alive.
+    pub population: u32,
+    /// People who have died, whether or not it was during the simulation.
+    pub dead: u32,
+    pub births: u32,
+    pub deaths: u32,
+    /// Everything in the history, including births and deaths.
+    pub events: u32,
+}
+
+impl BranchSummary {
+    pub(super) fn of_file(name: &str, file: &WorldFile) -> Self {
+        let people = file.entities.iter().filter(|record| record.person);
+        let (mut population, mut dead) = (0, 0);
+        for person in people {
+            match person.living {
+                Some(Living::Dead) => dead += 1,
+                _ => population += 1,
+            }
+        }
+
+        let count = |kind: HistoryEventKind| file.history.iter().filter(|event| event.kind == kind).count() as u32;
+
+        Self {
+            name: name.to_string(),
+            time_elapsed: file.config.time_elapsed(),
+            seed: file.config.seed,
+            population,
+            dead,
+            births: count(HistoryEventKind::Birth),
+            deaths: count(HistoryEventKind::Death),
+            events: file.history.len() as u32,
+        }
+    }
+}
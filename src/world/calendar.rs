@@ -0,0 +1,183 @@
+//! The world's calendar, for turning time in the simulation into dates people can use.
+//!
+//! The simulation itself only deals in [Age]s, which count days. The calendar is mostly for display,
+//! but its months also decide the [Seasons](super::season::Seasons), which some systems depend on.
+
+use bevy::ecs::system::Resource;
+use serde::{Serialize, Deserialize};
+use super::time::Age;
+
+/// How the world counts its days, months, and years.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calendar {
+    /// Written after the year, like "AR" in "14 Frostmere, 1203 AR". This can be empty.
+    pub epoch: String,
+    /// The months of the year, in order. A year is as long as all of its months together.
+    pub months: Vec<Month>,
+    /// The date the simulation starts on.
+    pub start: CalendarDate,
+}
+
+/// A month of the [Calendar].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Month {
+    pub name: String,
+    pub days: u32,
+}
+
+/// A day in a [Calendar].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CalendarDate {
+    pub year: i32,
+    /// The index of the month in the calendar, starting from zero.
+    pub month: usize,
+    /// The day of the month, starting from one.
+    pub day: u32,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        // Thirty day months, to match how ages are counted
+        let names = [
+            "January", "February", "March", "April", "May", "June",
+            "July", "August", "September", "October", "November", "December",
+        ];
+
+        Self {
+            epoch: String::new(),
+            months: names.iter().map(|name| Month { name: name.to_string(), days: 30 }).collect(),
+            start: CalendarDate::default(),
+        }
+    }
+}
+
+impl Default for CalendarDate {
+    fn default() -> Self {
+        Self { year: 1, month: 0, day: 1 }
+    }
+}
+
+impl Calendar {
+    /// Returns how many days there are in a year.
+    pub fn year_length(&self) -> u32 {
+        self.months.iter().map(|month| month.days).sum()
+    }
+
+    /// Returns how many days into its year a date is, starting from zero.
+    /// Dates that don't exist in this calendar are moved to the nearest one that does.
+    fn day_of_year(&self, date: CalendarDate) -> u32 {
+        let month = date.month.min(self.months.len().saturating_sub(1));
+        let before: u32 = self.months.iter().take(month).map(|month| month.days).sum();
+        let length = self.months.get(month).map_or(1, |month| month.days.max(1));
+        before + date.day.clamp(1, length) - 1
+    }
+
+    /// Returns the date `days` after the start of the simulation. Negative numbers go back in time.
+    pub fn date_offset(&self, days: i64) -> CalendarDate {
+        let length = self.year_length() as i64;
+        if length == 0 { return self.start; }
+
+        let total = self.start.year as i64 * length + self.day_of_year(self.start) as i64 + days;
+        let year = total.div_euclid(length) as i32;
+        let mut remaining = total.rem_euclid(length) as u32;
+
+        for (index, month) in self.months.iter().enumerate() {
+            if remaining < month.days {
+                return CalendarDate { year, month: index, day: remaining + 1 };
+            }
+            remaining -= month.days;
+        }
+
+        // Only reachable if the months changed underneath us
+        CalendarDate { year, month: 0, day: 1 }
+    }
+
+    /// Returns how many days after the start of the simulation `date` is. Dates before the start are negative.
+    /// Dates that don't exist in this calendar are moved to the nearest one that does.
+    pub fn days_until(&self, date: CalendarDate) -> i64 {
+        let length = self.year_length() as i64;
+        let total = |date: CalendarDate| date.year as i64 * length + self.day_of_year(date) as i64;
+        total(date) - total(self.start)
+    }
+
+    /// Returns the date after `elapsed` time has passed since the start of the simulation.
+    pub fn date_after(&self, elapsed: Age) -> CalendarDate {
+        self.date_offset(elapsed.days_passed() as i64)
+    }
+
+    /// Writes a date like "14 Frostmere, 1203 AR".
+    pub fn format(&self, date: CalendarDate) -> String {
+        let month = match self.months.get(date.month) {
+            Some(month) => month.name.clone(),
+            None => format!("month {}", date.month + 1),
+        };
+
+        match self.epoch.trim().is_empty() {
+            true => format!("{} {}, {}", date.day, month, date.year),
+            false => format!("{} {}, {} {}", date.day, month, date.year, self.epoch.trim()),
+        }
+    }
+
+    /// Writes the date after `elapsed` time has passed since the start of the simulation.
+    pub fn format_after(&self, elapsed: Age) -> String {
+        self.format(self.date_after(elapsed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A calendar with a long month and a short one, starting part way through the year.
+    fn uneven() -> Calendar {
+        Calendar {
+            epoch: "AR".into(),
+            months: vec![Month { name: "Frostmere".into(), days: 20 }, Month { name: "Thaw".into(), days: 10 }],
+            start: CalendarDate { year: 1203, month: 0, day: 14 },
+        }
+    }
+
+    #[test]
+    fn custom_months() {
+        let calendar = uneven();
+        assert_eq!(calendar.year_length(), 30);
+        assert_eq!(calendar.format_after(Age::ZERO), "14 Frostmere, 1203 AR");
+        assert_eq!(calendar.format_after(Age::from_days(7)), "1 Thaw, 1203 AR");
+        assert_eq!(calendar.format_after(Age::from_days(16)), "10 Thaw, 1203 AR");
+        assert_eq!(calendar.format_after(Age::from_days(17)), "1 Frostmere, 1204 AR");
+    }
+
+    #[test]
+    fn negative_offsets() {
+        let calendar = uneven();
+        assert_eq!(calendar.date_offset(-13), CalendarDate { year: 1203, month: 0, day: 1 });
+        assert_eq!(calendar.date_offset(-14), CalendarDate { year: 1202, month: 1, day: 10 });
+        assert_eq!(calendar.date_offset(-44), CalendarDate { year: 1201, month: 1, day: 10 });
+
+        // Going back past year zero counts down into negative years
+        let calendar = Calendar { start: CalendarDate { year: 0, month: 0, day: 1 }, ..uneven() };
+        assert_eq!(calendar.date_offset(-1), CalendarDate { year: -1, month: 1, day: 10 });
+    }
+
+    #[test]
+    fn days_until_undoes_offsets() {
+        let calendar = uneven();
+        for days in [-100, -14, -1, 0, 1, 7, 17, 1000] {
+            assert_eq!(calendar.days_until(calendar.date_offset(days)), days);
+        }
+    }
+
+    #[test]
+    fn default_calendar_matches_ages() {
+        let calendar = Calendar::default();
+        assert_eq!(calendar.year_length(), Age::from_years(1).days_passed());
+        assert_eq!(calendar.format_after(Age::from_years(2)), "1 January, 3");
+    }
+
+    #[test]
+    fn missing_start_day_is_moved() {
+        let calendar = Calendar { start: CalendarDate { year: 5, month: 1, day: 40 }, ..uneven() };
+        assert_eq!(calendar.date_offset(0), CalendarDate { year: 5, month: 1, day: 10 });
+    }
+}
@@ -1,13 +1,14 @@
 //! Qualities common to a lot of entities, like their name or age.
 
 use bevy::ecs::prelude::*;
-use super::{living::Living, time::Age, defs::{SimulationConfig, Timespan}};
+use serde::{Serialize, Deserialize};
+use super::{living::Living, time::Age, defs::SimulationConfig};
 
 /// Any entities with this component will have more in-depth information generated.
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Important;
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Name(pub String);
 
 /// Increments the age value each tick.
@@ -19,9 +20,6 @@ pub(super) fn age_incrementor_system(
         // Don't age dead things.
         if status.is_some() && *status.unwrap() == Living::Dead { continue; }
 
-        match config.timespan {
-            Timespan::Months => age.add_months(1),
-            Timespan::Days => age.add_days(1),
-        }
+        age.add_days(config.days_per_tick());
     }
 }
\ No newline at end of file
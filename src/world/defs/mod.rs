@@ -1,11 +1,15 @@
 pub mod species;
 
 use bevy::ecs::system::Resource;
+use serde::{Serialize, Deserialize};
+use super::{calendar::{Calendar, CalendarDate}, soft_limits, time::Age};
 
 /// Overarching information about the world.
-#[derive(Resource, Debug)]
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationConfig {
     /// Prevents the user from changing any below values in the UI.
+    /// This isn't saved, since the simulation systems aren't either.
+    #[serde(skip)]
     pub locked_in: bool,
 
     pub name: String,
@@ -14,10 +18,62 @@ pub struct SimulationConfig {
     pub timespan: Timespan,
     pub increments_completed: u32,
     pub increments_for_completion: u32,
+    /// How many increments pass between checkpoints. No checkpoints are made if this is zero.
+    #[serde(default = "default_checkpoint_interval")]
+    pub checkpoint_interval: u32,
+}
+
+fn default_checkpoint_interval() -> u32 {
+    100
+}
+
+impl SimulationConfig {
+    /// Returns how many in-world days pass each tick.
+    /// Systems should use this rather than matching on the timespan.
+    pub fn days_per_tick(&self) -> u32 {
+        self.timespan.days()
+    }
+
+    /// Returns how much in-world time has passed since the simulation started.
+    pub fn time_elapsed(&self) -> Age {
+        Age::from_days(self.increments_completed.saturating_mul(self.days_per_tick()))
+    }
+
+    /// Returns how many ticks it takes for `time` to pass, rounding up.
+    pub fn increments_for(&self, time: Age) -> u32 {
+        time.days_passed().div_ceil(self.days_per_tick())
+    }
+
+    /// Returns how much in-world time passes over the whole simulation.
+    pub fn run_length(&self) -> Age {
+        Age::from_days(self.increments_for_completion.saturating_mul(self.days_per_tick()))
+    }
+
+    /// Sets how much in-world time passes over the whole simulation, moved inside the [soft_limits].
+    /// Returns the length that was used.
+    pub fn set_run_length(&mut self, length: Age) -> Age {
+        let length = soft_limits::clamp(length);
+        self.increments_for_completion = self.increments_for(length);
+        length
+    }
+
+    /// Returns how far into the world's timeline the simulation is, which is what events are dated with.
+    /// Backwards runs start at the present, at the end of the timeline, and work back towards its start.
+    pub fn now(&self) -> Age {
+        match self.direction {
+            HistoryDirection::Forwards => self.time_elapsed(),
+            HistoryDirection::Backwards => Age::from_days(self.run_length().days_passed().saturating_sub(self.time_elapsed().days_passed())),
+        }
+    }
+
+    /// Returns what the date is now in the world.
+    pub fn current_date(&self, calendar: &Calendar) -> CalendarDate {
+        calendar.date_after(self.now())
+    }
 }
 
 /// The direction history generates from.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HistoryDirection {
     /// History generates forwards, as if time is progressing from a point in history.
     Forwards,
@@ -26,8 +82,70 @@ pub enum HistoryDirection {
 }
 
 /// The timestep in which the simulator operates.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Timespan {
-    Months,
     Days,
-}
\ No newline at end of file
+    Weeks,
+    Months,
+    /// A quarter of a year.
+    Seasons,
+    Years,
+}
+
+impl Timespan {
+    /// Returns how many in-world days are in one of this timespan.
+    pub fn days(&self) -> u32 {
+        match self {
+            Timespan::Days => 1,
+            Timespan::Weeks => 7,
+            Timespan::Months => 30,
+            Timespan::Seasons => 90,
+            Timespan::Years => 360,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::world::{soft_limits::{MIN_YEARS_TO_SIMULATE, MAX_YEARS_TO_SIMULATE}, time::Age};
+    use super::{SimulationConfig, HistoryDirection, Timespan};
+
+    fn config(timespan: Timespan) -> SimulationConfig {
+        SimulationConfig {
+            locked_in: false,
+            name: "".to_string(),
+            seed: 0,
+            direction: HistoryDirection::Forwards,
+            timespan,
+            increments_completed: 0,
+            increments_for_completion: 0,
+            checkpoint_interval: 100,
+        }
+    }
+
+    #[test]
+    fn run_length_rounds_up_to_whole_steps() {
+        let mut config = config(Timespan::Weeks);
+        config.set_run_length(Age::from_years(100));
+        assert_eq!(config.increments_for_completion, 36000 / 7 + 1);
+        assert!(config.run_length() >= Age::from_years(100));
+    }
+
+    #[test]
+    fn run_length_is_kept_within_soft_limits() {
+        let mut config = config(Timespan::Years);
+        assert_eq!(config.set_run_length(Age::from_years(5000)), Age::from_years(MAX_YEARS_TO_SIMULATE));
+        assert_eq!(config.increments_for_completion, MAX_YEARS_TO_SIMULATE);
+
+        assert_eq!(config.set_run_length(Age::from_days(10)), Age::from_years(MIN_YEARS_TO_SIMULATE));
+        assert_eq!(config.increments_for_completion, MIN_YEARS_TO_SIMULATE);
+    }
+
+    #[test]
+    fn lengths_do_not_overflow() {
+        let mut config = config(Timespan::Years);
+        config.increments_for_completion = u32::MAX;
+        config.increments_completed = u32::MAX;
+        assert_eq!(config.run_length(), Age::from_days(u32::MAX));
+        assert_eq!(config.time_elapsed(), Age::from_days(u32::MAX));
+    }
+}
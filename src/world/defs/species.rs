@@ -1,13 +1,18 @@
 use bevy::ecs::prelude::*;
+use serde::{Serialize, Deserialize};
 use crate::world::{common::Name, time::Age};
 
+// This is the age at which a humanoid is considered an 'adult', and therefore can reproduce.
+// DO NOT set this lower. No excuses.
+pub const MIN_HUMANOID_AGE: Age = Age::from_years(18);
+
 #[derive(Bundle)]
 pub struct SpeciesBundle {
     pub name: Name,
     pub species: Species,
 }
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Species {
     /// Is this species humanoid?
     pub humanoid: bool,
@@ -19,6 +24,48 @@ pub struct Species {
     pub resilience: f32,
     /// Modifier for the progression of affliction severity.
     pub immunity: f32,
+    /// How many children a couple of this species has per year, on average.
+    #[serde(default)]
+    pub fertility: f32,
+    /// The age after which this creature can no longer reproduce.
+    #[serde(default)]
+    pub fertile_until: Age,
+    /// How harm from old age builds up as this creature approaches `max_age`.
+    #[serde(default)]
+    pub aging: AgingCurve,
+}
+
+/// How harm from old age builds up as a creature approaches its species' `max_age`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgingCurve {
+    /// The fraction of `max_age` at which old age starts causing harm.
+    pub onset: f32,
+    /// How sharply harm rises after the onset. `1.0` is linear, and higher values leave most of the harm until the end.
+    pub steepness: f32,
+}
+
+impl AgingCurve {
+    /// Returns how much old age weakens a creature. Afflictions are this much worse on top of their usual effect,
+    /// and creatures die of old age once it passes `1.0`.
+    /// This reaches `1.0` at `max_age` and keeps rising beyond it.
+    pub fn harm(&self, age: Age, max_age: Age) -> f32 {
+        if max_age == Age::ZERO { return 0.0; }
+
+        let onset = self.onset.clamp(0.0, 0.99);
+        let ratio = age.days_passed() as f32 / max_age.days_passed() as f32;
+        if ratio <= onset { return 0.0; }
+
+        ((ratio - onset) / (1.0 - onset)).powf(self.steepness)
+    }
+}
+
+impl Default for AgingCurve {
+    fn default() -> Self {
+        Self {
+            onset: 0.6,
+            steepness: 2.0,
+        }
+    }
 }
 
 #[derive(Component, Clone, PartialEq, Eq)]
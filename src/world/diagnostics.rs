@@ -0,0 +1,251 @@
+//! Problems found in a world before it's simulated.
+
+use std::{collections::BTreeSet, fmt::Display};
+use bevy::{ecs::prelude::*, prelude::Parent};
+use super::{
+    calendar::Calendar,
+    common::Name,
+    defs::{HistoryDirection, SimulationConfig, Timespan, species::{AssociatedSpecies, Species, MIN_HUMANOID_AGE}},
+    living::afflictions::{Afflicted, Affliction},
+    person::{Person, kinship::{Parents, Partners}},
+    place::{Region, Settlement},
+    season::Seasons,
+    soft_limits::{self, MIN_YEARS_TO_SIMULATE, MAX_YEARS_TO_SIMULATE},
+    stop::StopConditions,
+    time::Age,
+};
+
+/// How bad a [Diagnostic] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The simulation can't run until this is fixed.
+    Error,
+    /// The simulation can run, but probably won't do what was intended.
+    Warning,
+}
+
+/// A problem with something in the world.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The entity with the problem, if there is one in particular.
+    pub entity: Option<Entity>,
+    /// A description of the problem, intended for people to read.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(entity: Entity, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, entity: Some(entity), message: message.into() }
+    }
+
+    fn warning(entity: Entity, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, entity: Some(entity), message: message.into() }
+    }
+
+    /// A warning about the world as a whole, rather than one entity in it.
+    fn world_warning(message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, entity: None, message: message.into() }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.entity {
+            Some(entity) => write!(f, "{:?} ({:?}): {}", self.severity, entity, self.message),
+            None => write!(f, "{:?}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Returns `true` if any of the diagnostics stop the simulation from running.
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+/// Returns the name of an entity for use in messages.
+fn describe(world: &World, entity: Entity) -> String {
+    match world.get::<Name>(entity) {
+        Some(name) => format!("'{}'", name.0),
+        None => format!("{:?}", entity),
+    }
+}
+
+/// People and creatures whose species doesn't exist, or who are older than their species allows.
+pub(super) fn check_species(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let mut query = world.query::<(Entity, &AssociatedSpecies, Option<&Age>)>();
+    for (entity, associated_species, age) in query.iter(world) {
+        let Some(species) = world.get::<Species>(associated_species.0) else {
+            diagnostics.push(Diagnostic::error(entity, format!("{} has a species that doesn't exist ({:?})", describe(world, entity), associated_species.0)));
+            continue;
+        };
+
+        if let Some(age) = age {
+            if *age > species.max_age {
+                diagnostics.push(Diagnostic::warning(entity, format!(
+                    "{} is {}, which is older than the maximum age of {} ({}), and will die almost immediately",
+                    describe(world, entity), age, describe(world, associated_species.0), species.max_age,
+                )));
+            }
+        }
+    }
+
+    let mut query = world.query::<(Entity, &Species)>();
+    for (entity, species) in query.iter(world) {
+        if species.humanoid && species.maturity_age < MIN_HUMANOID_AGE {
+            diagnostics.push(Diagnostic::error(entity, format!(
+                "{} is humanoid, but matures at {}, which is below the minimum of {}",
+                describe(world, entity), species.maturity_age, MIN_HUMANOID_AGE,
+            )));
+        }
+    }
+}
+
+/// Afflictions that point to entities that aren't afflictions, and afflictions that can't be saved as they are.
+pub(super) fn check_afflictions(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let mut query = world.query::<(Entity, &Afflicted)>();
+    for (entity, afflicted) in query.iter(world) {
+        for (affliction, _) in afflicted.iter() {
+            if world.get::<Affliction>(*affliction).is_some() { continue; }
+            diagnostics.push(Diagnostic::error(entity, format!("{} is afflicted by something that isn't an affliction ({:?})", describe(world, entity), affliction)));
+        }
+    }
+
+    let mut query = world.query::<(Entity, &Affliction)>();
+    for (entity, affliction) in query.iter(world) {
+        if affliction.saveable() { continue; }
+        diagnostics.push(Diagnostic::warning(entity, format!("{} uses a custom function, which will be saved as no adjustment", describe(world, entity))));
+    }
+}
+
+/// Settlements outside of regions, and loops in the hierarchy.
+pub(super) fn check_hierarchy(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let mut query = world.query_filtered::<(Entity, &Parent), With<Settlement>>();
+    for (entity, parent) in query.iter(world) {
+        if world.get::<Region>(parent.get()).is_some() { continue; }
+        diagnostics.push(Diagnostic::error(entity, format!("{} is a settlement, but its parent {} isn't a region", describe(world, entity), describe(world, parent.get()))));
+    }
+
+    // Follow each chain of parents, reporting every entity that ends up back where it started
+    let mut query = world.query::<(Entity, &Parent)>();
+    for (entity, _) in query.iter(world) {
+        let mut visited: BTreeSet<Entity> = BTreeSet::from([entity]);
+        let mut current = entity;
+        while let Some(parent) = world.get::<Parent>(current) {
+            current = parent.get();
+            if current == entity {
+                diagnostics.push(Diagnostic::error(entity, format!("{} is inside itself in the hierarchy", describe(world, entity))));
+                break;
+            }
+            // A loop further up, which will be reported for the entities in it
+            if !visited.insert(current) { break; }
+        }
+    }
+}
+
+/// Family links to people that no longer exist.
+pub(super) fn check_kinship(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let mut query = world.query::<(Entity, Option<&Parents>, Option<&Partners>)>();
+    for (entity, parents, partners) in query.iter(world) {
+        let linked = parents.iter().flat_map(|x| x.0.iter()).chain(partners.iter().flat_map(|x| x.0.iter()));
+        for relative in linked {
+            if world.get_entity(*relative).is_some() { continue; }
+            diagnostics.push(Diagnostic::warning(entity, format!("{} has a relative that no longer exists ({:?})", describe(world, entity), relative)));
+        }
+    }
+}
+
+/// Calendars that can't show dates properly.
+pub(super) fn check_calendar(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(calendar) = world.get_resource::<Calendar>() else { return };
+
+    if calendar.year_length() == 0 {
+        diagnostics.push(Diagnostic::world_warning("The calendar has no days in its year, so every date will be the start date"));
+        return;
+    }
+
+    if let Some(config) = world.get_resource::<SimulationConfig>() {
+        if config.timespan == Timespan::Years && calendar.year_length() != config.days_per_tick() {
+            diagnostics.push(Diagnostic::world_warning(format!(
+                "Each step is a year of {} days, but the calendar's year is {} days long, so steps will drift through the calendar's months",
+                config.days_per_tick(), calendar.year_length(),
+            )));
+        }
+    }
+
+    for month in calendar.months.iter().filter(|month| month.days == 0) {
+        diagnostics.push(Diagnostic::world_warning(format!("The calendar month '{}' has no days, so it will never be reached", month.name)));
+    }
+
+    let start = calendar.start;
+    match calendar.months.get(start.month) {
+        Some(month) if start.day >= 1 && start.day <= month.days => {},
+        _ => diagnostics.push(Diagnostic::world_warning(format!(
+            "The calendar starts on a day that doesn't exist, so it will start on {} instead",
+            calendar.format(calendar.date_offset(0)),
+        ))),
+    }
+}
+
+/// Checks that every season starts in a month of the calendar, and that no two start together.
+pub(super) fn check_seasons(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(seasons) = world.get_resource::<Seasons>() else { return };
+    let months = world.get_resource::<Calendar>().map_or(0, |calendar| calendar.months.len());
+
+    let mut starts = BTreeSet::new();
+    for season in seasons.0.iter() {
+        if season.first_month >= months {
+            diagnostics.push(Diagnostic::world_warning(format!("The season '{}' starts in a month the calendar doesn't have", season.name)));
+        } else if !starts.insert(season.first_month) {
+            diagnostics.push(Diagnostic::world_warning(format!("The season '{}' starts in the same month as another season, so one of them will never happen", season.name)));
+        }
+    }
+}
+
+/// Checks the simulation runs for a length of time inside the [soft_limits].
+pub(super) fn check_run_length(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(config) = world.get_resource::<SimulationConfig>() else { return };
+    let length = config.run_length();
+
+    if !soft_limits::contains(length) {
+        diagnostics.push(Diagnostic::world_warning(format!(
+            "The simulation runs for {}, but should run for between {} and {} years",
+            length, MIN_YEARS_TO_SIMULATE, MAX_YEARS_TO_SIMULATE,
+        )));
+    }
+
+    // Backwards runs only go back as far as the start, so anything older is squashed onto it
+    if config.direction == HistoryDirection::Backwards {
+        let mut people = world.query_filtered::<&Age, With<Person>>();
+        let older = people.iter(world).filter(|age| **age > length).count();
+        let who = match older {
+            0 => return,
+            1 => "1 person is".to_string(),
+            older => format!("{} people are", older),
+        };
+        diagnostics.push(Diagnostic::world_warning(format!(
+            "{} older than the {} of history being generated, so their births will be recorded on the first day",
+            who, length,
+        )));
+    }
+}
+
+/// Checks that stop conditions refer to things that exist, and aren't already met.
+pub(super) fn check_stop_conditions(world: &mut World, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(conditions) = world.get_resource::<StopConditions>().cloned() else { return };
+
+    let mut query = world.query::<&Name>();
+    let names: BTreeSet<String> = query.iter(world).map(|name| name.0.clone()).collect();
+
+    for condition in conditions.0.iter() {
+        match condition.subject() {
+            _ if condition.met(world) => diagnostics.push(Diagnostic::world_warning(format!(
+                "The stop condition '{}' is already met, so it won't stop the simulation", condition,
+            ))),
+            Some(subject) if !names.contains(subject) => diagnostics.push(Diagnostic::world_warning(format!(
+                "The stop condition '{}' is about something that doesn't exist, so it will never be met", condition,
+            ))),
+            _ => {},
+        }
+    }
+}
@@ -1 +1,115 @@
-//! An event in history.
\ No newline at end of file
+//! An event in history.
+
+use bevy::ecs::{prelude::Entity, system::Resource};
+use serde::{Serialize, Deserialize};
+use super::time::Age;
+
+/// Something that happened in history.
+#[derive(Debug, Clone)]
+pub struct HistoryEvent {
+    /// How far into the world's timeline this happened. See [SimulationConfig::now](super::defs::SimulationConfig::now).
+    pub date: Age,
+    /// The simulation tick this was recorded on.
+    pub tick: u32,
+    /// What sort of event this is.
+    pub kind: HistoryEventKind,
+    /// The entities involved in this event. The first one is the main subject.
+    pub participants: Vec<Entity>,
+    /// Where this event happened, if anywhere in particular.
+    pub place: Option<Entity>,
+    /// A short description of what happened, intended for people to read.
+    pub summary: String,
+}
+
+/// The different kinds of [HistoryEvent].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum HistoryEventKind {
+    /// A person was born.
+    Birth,
+    /// A living creature died.
+    Death,
+    /// Two people became partners.
+    Partnership,
+    /// A living creature gained an affliction.
+    AfflictionContracted,
+    /// A living creature recovered from an affliction.
+    AfflictionRecovered,
+    /// The population of a settlement changed.
+    PopulationChange,
+    /// Something was moved to a different place in the hierarchy.
+    HierarchyChange,
+    /// A settlement was founded.
+    Founding,
+}
+
+impl HistoryEventKind {
+    pub const ALL: [HistoryEventKind; 8] = [
+        HistoryEventKind::Birth,
+        HistoryEventKind::Death,
+        HistoryEventKind::Partnership,
+        HistoryEventKind::AfflictionContracted,
+        HistoryEventKind::AfflictionRecovered,
+        HistoryEventKind::PopulationChange,
+        HistoryEventKind::HierarchyChange,
+        HistoryEventKind::Founding,
+    ];
+
+    /// A name for this kind of event, intended for people to read.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistoryEventKind::Birth => "Birth",
+            HistoryEventKind::Death => "Death",
+            HistoryEventKind::Partnership => "Partnership",
+            HistoryEventKind::AfflictionContracted => "Affliction contracted",
+            HistoryEventKind::AfflictionRecovered => "Affliction recovered",
+            HistoryEventKind::PopulationChange => "Population change",
+            HistoryEventKind::HierarchyChange => "Hierarchy change",
+            HistoryEventKind::Founding => "Founding",
+        }
+    }
+}
+
+/// Every [HistoryEvent] that has been recorded, in the order they were recorded.
+#[derive(Resource, Debug, Default)]
+pub struct History {
+    events: Vec<HistoryEvent>,
+}
+
+impl History {
+    /// Adds an event to the end of history.
+    pub fn record(&mut self, event: HistoryEvent) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HistoryEvent> {
+        self.events.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns all events that `entity` took part in.
+    pub fn involving(&self, entity: Entity) -> impl Iterator<Item = &HistoryEvent> {
+        self.iter().filter(move |event| event.participants.contains(&entity))
+    }
+
+    /// Returns all events that happened at `place`.
+    pub fn at_place(&self, place: Entity) -> impl Iterator<Item = &HistoryEvent> {
+        self.iter().filter(move |event| event.place == Some(place))
+    }
+
+    /// Returns all events of a certain kind.
+    pub fn of_kind(&self, kind: HistoryEventKind) -> impl Iterator<Item = &HistoryEvent> {
+        self.iter().filter(move |event| event.kind == kind)
+    }
+
+    /// Returns all events that happened between `start` and `end`, inclusive.
+    pub fn between(&self, start: Age, end: Age) -> impl Iterator<Item = &HistoryEvent> {
+        self.iter().filter(move |event| event.date >= start && event.date <= end)
+    }
+}
@@ -5,7 +5,7 @@ use bevy::prelude::*;
 use super::person::Personality;
 
 #[allow(dead_code)]
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct Faction {
     /// An offset to the individual personalities of its members.
     personality_offset: Personality,
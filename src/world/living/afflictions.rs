@@ -1,11 +1,12 @@
 //! Values for calculating health.
 
-use std::{fmt::Debug, collections::{BTreeMap, btree_map::Iter}};
-use bevy::prelude::{Component, Entity, Bundle, Query, Res};
-use crate::world::{common::Name, defs::{SimulationConfig, Timespan}};
+use std::{fmt::Debug, collections::{BTreeMap, BTreeSet, btree_map::Iter}};
+use bevy::prelude::{Component, Entity, Bundle, Query, Res, ResMut, Local, Changed};
+use serde::{Serialize, Deserialize};
+use crate::world::{common::Name, defs::SimulationConfig, event::{History, HistoryEvent, HistoryEventKind}, season::CurrentSeason};
 
 /// A value for an affliction that changes depending on severity.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum SeverityVariableValue {
     /// Equal to `Static(0.0)` as a flat rate and `Static(1.0)` as a coefficient.
     NoAdjustment,
@@ -14,6 +15,8 @@ pub enum SeverityVariableValue {
     /// Always remains this value and ignores severity.
     Static(f32),
     /// A Rust function that accepts severity and outputs the new value.
+    /// This can't be written to a world file, see [SeverityVariableValue::to_saveable].
+    #[serde(skip)]
     Custom(Box<dyn SeverityVariableFn>),
 }
 
@@ -27,6 +30,22 @@ impl SeverityVariableValue {
             SeverityVariableValue::Custom(value) => { return value(severity) },
         }
     }
+
+    /// Returns `true` if this value is written to a world file as it is.
+    pub fn saveable(&self) -> bool {
+        !matches!(self, SeverityVariableValue::Custom(_))
+    }
+
+    /// Returns a copy of this value that can be written to a world file.
+    /// Functions can't be saved, so `Custom` becomes `NoAdjustment`.
+    pub fn to_saveable(&self) -> Self {
+        match self {
+            SeverityVariableValue::NoAdjustment => SeverityVariableValue::NoAdjustment,
+            SeverityVariableValue::Scaling(value) => SeverityVariableValue::Scaling(*value),
+            SeverityVariableValue::Static(value) => SeverityVariableValue::Static(*value),
+            SeverityVariableValue::Custom(_) => SeverityVariableValue::NoAdjustment,
+        }
+    }
 }
 
 /// Auto-trait for [SeverityVariableValue]
@@ -41,17 +60,33 @@ impl<T: Debug + Send + Sync + Fn(f32) -> f32> SeverityVariableFn for T {}
 /// First, all the flat rate changes are applied.
 /// Second, all the coefficients are applied.
 /// The cached `Health` value is based on the associated species of the creature.
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize)]
 pub struct Affliction {
     /// Added to a value that is later added to the health.
     pub flat: SeverityVariableValue,
     /// Applied to the flat rate modifier after they're summed up.
     pub coefficient: SeverityVariableValue,
     /// Defines the speed of progression for this disease.
-    /// This is applied every tick to values in [Afflicted] based on days. If the sim timespan is months, it's multiplied by 30.
+    /// This is applied every tick to values in [Afflicted] based on days, so it's multiplied by the number of days in each tick.
     pub progression_speed: SeverityVariableValue,
 }
 
+impl Affliction {
+    /// Returns `true` if every value of this affliction is written to a world file as it is.
+    pub fn saveable(&self) -> bool {
+        self.flat.saveable() && self.coefficient.saveable() && self.progression_speed.saveable()
+    }
+
+    /// Returns a copy of this affliction that can be written to a world file, see [SeverityVariableValue::to_saveable].
+    pub fn to_saveable(&self) -> Self {
+        Self {
+            flat: self.flat.to_saveable(),
+            coefficient: self.coefficient.to_saveable(),
+            progression_speed: self.progression_speed.to_saveable(),
+        }
+    }
+}
+
 impl Default for Affliction {
     fn default() -> Self {
         Self {
@@ -69,7 +104,7 @@ pub struct AfflictionBundle {
 }
 
 #[derive(Debug, Component)]
-pub struct Afflicted(BTreeMap<Entity, f32>);
+pub struct Afflicted(pub BTreeMap<Entity, f32>);
 
 impl Afflicted {
     pub fn iter(&self) -> Iter<Entity, f32> {
@@ -79,20 +114,82 @@ impl Afflicted {
 
 pub(in super::super) fn affliction_progress_system(
     config: Res<SimulationConfig>,
+    season: Res<CurrentSeason>,
+    mut history: ResMut<History>,
     afflictions: Query<&Affliction>,
-    mut afflicted: Query<&mut Afflicted>,
+    mut afflicted: Query<(Entity, &mut Afflicted)>,
+    names: Query<&Name>,
 ) {
-    for mut afflicted in afflicted.iter_mut() {
+    for (entity, mut afflicted) in afflicted.iter_mut() {
         for (k, v) in afflicted.0.iter_mut() {
             if let Ok(affliction) = afflictions.get(*k) {
                 // Calculate affliction change
                 let mut adjust = affliction.progression_speed.effect(false, *v);
                 if adjust == 0.0 { continue; }
-                if config.timespan == Timespan::Months { adjust *= 30.0; }
+                adjust *= config.days_per_tick() as f32;
+
+                // Harsh seasons make afflictions worse faster and slow down recovery
+                if adjust > 0.0 { adjust *= season.affliction_modifier; } else { adjust /= season.affliction_modifier.max(0.01); }
 
                 // Apply change
+                let previous = *v;
                 *v += adjust;
+
+                // Record recoveries
+                if previous > 0.0 && *v <= 0.0 {
+                    let name = names.get(entity).map(|name| name.0.as_str()).unwrap_or("Someone");
+                    let affliction_name = names.get(*k).map(|name| name.0.as_str()).unwrap_or("an affliction");
+                    history.record(HistoryEvent {
+                        date: config.time_elapsed(),
+                        tick: config.increments_completed,
+                        kind: HistoryEventKind::AfflictionRecovered,
+                        participants: vec![entity, *k],
+                        place: None,
+                        summary: format!("{} recovered from {}.", name, affliction_name),
+                    });
+                }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Remembers which afflictions everything had last tick, for [affliction_contraction_system].
+#[derive(Default)]
+pub(in super::super) struct KnownAfflictions {
+    initialised: bool,
+    afflictions: BTreeMap<Entity, BTreeSet<Entity>>,
+}
+
+/// Records newly gained afflictions in history.
+/// Afflictions that already existed when the simulation started aren't recorded.
+pub(in super::super) fn affliction_contraction_system(
+    config: Res<SimulationConfig>,
+    mut history: ResMut<History>,
+    mut known: Local<KnownAfflictions>,
+    afflicted: Query<(Entity, &Afflicted), Changed<Afflicted>>,
+    names: Query<&Name>,
+) {
+    let initialised = known.initialised;
+    known.initialised = true;
+
+    for (entity, afflicted) in afflicted.iter() {
+        let previous = known.afflictions.entry(entity).or_default();
+        for affliction in afflicted.0.keys() {
+            if !previous.insert(*affliction) || !initialised { continue; }
+
+            let name = names.get(entity).map(|name| name.0.as_str()).unwrap_or("Someone");
+            let affliction_name = names.get(*affliction).map(|name| name.0.as_str()).unwrap_or("an affliction");
+            history.record(HistoryEvent {
+                date: config.time_elapsed(),
+                tick: config.increments_completed,
+                kind: HistoryEventKind::AfflictionContracted,
+                participants: vec![entity, *affliction],
+                place: None,
+                summary: format!("{} contracted {}.", name, affliction_name),
+            });
+        }
+
+        // Forget afflictions that were removed, so getting them again is recorded
+        previous.retain(|affliction| afflicted.0.contains_key(affliction));
+    }
+}
@@ -1,10 +1,11 @@
 use bevy::prelude::*;
-use crate::world::defs::species::{AssociatedSpecies, Species};
+use serde::{Serialize, Deserialize};
+use crate::world::{common::Name, defs::{SimulationConfig, species::{AssociatedSpecies, Species}}, event::{History, HistoryEvent, HistoryEventKind}, time::Age};
 use super::{afflictions::{Afflicted, Affliction}, Living};
 
 /// Component that caches the health value of a living creature, intended for fast reading.
 /// This component is changed automatically when health factors change.
-#[derive(Debug, Component, Clone)]
+#[derive(Debug, Component, Clone, Serialize, Deserialize)]
 pub struct CachedHealth(f32);
 
 impl CachedHealth {
@@ -12,20 +13,20 @@ impl CachedHealth {
         Self(f32::INFINITY)
     }
 
-    #[allow(dead_code)]
     pub const fn read(&self) -> f32 {
         self.0
     }
 }
 
 /// Updates [CachedHealth] when its modifying values change.
+/// Health is the effect of afflictions scaled by the species' resilience, and made worse by old age.
 pub(in super::super) fn health_caching_system(
-    mut entities_query: Query<(&mut CachedHealth, Option<&AssociatedSpecies>, Option<&Afflicted>), Or<(Changed<AssociatedSpecies>, Changed<Afflicted>)>>,
+    mut entities_query: Query<(&mut CachedHealth, Option<&AssociatedSpecies>, Option<&Afflicted>, Option<&Age>), Or<(Changed<AssociatedSpecies>, Changed<Afflicted>, Changed<Age>)>>,
     // TODO: Re-cache if affliction or species definitions change.
     afflictions: Query<&Affliction>,
     species_query: Query<&Species>,
 ) {
-    for (mut health, species, afflicted) in entities_query.iter_mut() {
+    for (mut health, species, afflicted, age) in entities_query.iter_mut() {
         let mut adjust: f32 = 0.0;
 
         let mut coefficient: f32 = 1.0;
@@ -48,7 +49,11 @@ pub(in super::super) fn health_caching_system(
         if let Some(species) = species {
             let q = species_query.get(species.0);
             if q.is_ok() {
-                health.0 = q.unwrap().resilience * adjust;
+                let species = q.unwrap();
+                let harm = age.map_or(0.0, |age| species.aging.harm(*age, species.max_age));
+
+                // Old age makes afflictions hit harder, and is fatal by itself past the max age
+                health.0 = species.resilience * (adjust * (1.0 + harm) - (harm - 1.0).max(0.0));
             } else {
                 health.0 = 100.0 * adjust;
             }
@@ -58,11 +63,42 @@ pub(in super::super) fn health_caching_system(
 
 /// Kills living things. What did you expect?
 pub(in super::super) fn death_system(
+    config: Res<SimulationConfig>,
+    mut history: ResMut<History>,
     mut living: Query<&mut Living>,
-    health: Query<(Entity, &CachedHealth), (With<Living>, Changed<CachedHealth>)>,
+    health: Query<(Entity, &CachedHealth, Option<&Afflicted>, Option<&Parent>), (With<Living>, Changed<CachedHealth>)>,
+    names: Query<&Name>,
+    ages: Query<(&Age, &AssociatedSpecies)>,
+    species_query: Query<&Species>,
 ) {
-    for (entity, health) in &health {
+    for (entity, health, afflicted, parent) in &health {
         let mut living = living.get_mut(entity).unwrap();
-        if health.read() < 0.0 { *living = Living::Dead; }
+        if health.read() >= 0.0 || *living == Living::Dead { continue; }
+        *living = Living::Dead;
+
+        // Blame the most severe affliction, or old age if there isn't one
+        let cause = afflicted
+            .and_then(|afflicted| afflicted.iter().filter(|(_, severity)| **severity > 0.0).max_by(|a, b| a.1.total_cmp(b.1)))
+            .and_then(|(affliction, _)| names.get(*affliction).ok())
+            .map(|name| name.0.as_str());
+        let old_age = ages.get(entity).ok()
+            .and_then(|(age, species)| Some((age, species_query.get(species.0).ok()?)))
+            .is_some_and(|(age, species)| species.aging.harm(*age, species.max_age) > 0.0);
+
+        let name = names.get(entity).map(|name| name.0.as_str()).unwrap_or("Someone");
+        let summary = match (cause, old_age) {
+            (Some(cause), _) => format!("{} died of {}.", name, cause),
+            (None, true) => format!("{} died of old age.", name),
+            (None, false) => format!("{} died.", name),
+        };
+
+        history.record(HistoryEvent {
+            date: config.time_elapsed(),
+            tick: config.increments_completed,
+            kind: HistoryEventKind::Death,
+            participants: vec![entity],
+            place: parent.map(|parent| parent.get()),
+            summary,
+        });
     }
 }
\ No newline at end of file
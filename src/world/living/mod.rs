@@ -4,10 +4,11 @@ pub mod afflictions;
 pub mod health;
 
 use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
 
 /// Anything with this component will be considered 'living' and its behavior will change.
 /// This includes age not incrementing when dead.
-#[derive(Debug, Component, Clone, PartialEq, Eq)]
+#[derive(Debug, Component, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Living {
     Alive,
     Dead,
@@ -1,6 +1,19 @@
 pub mod sim;
 pub mod presets;
+pub mod diagnostics;
+pub mod branch;
+pub mod snapshot;
+pub mod protocol;
+pub mod stats;
+pub mod rng;
+pub mod save;
 pub mod time;
+pub mod calendar;
+pub mod season;
+pub mod stop;
+pub mod past;
+#[cfg(test)]
+pub mod testing;
 
 pub mod common;
 pub mod event;
@@ -10,9 +23,23 @@ pub mod defs;
 pub mod living;
 pub mod faction;
 
+/// How long a simulation can be set to run for. Lengths outside these are moved inside them when they're set,
+/// and worlds that already run for longer or shorter are warned about.
 pub mod soft_limits {
+    use super::time::Age;
+
     pub const MIN_YEARS_TO_SIMULATE: u32 = 50;
     pub const MAX_YEARS_TO_SIMULATE: u32 = 1000;
+
+    /// Returns `length` moved inside the limits.
+    pub fn clamp(length: Age) -> Age {
+        length.clamp(Age::from_years(MIN_YEARS_TO_SIMULATE), Age::from_years(MAX_YEARS_TO_SIMULATE))
+    }
+
+    /// Returns `true` if `length` is inside the limits.
+    pub fn contains(length: Age) -> bool {
+        clamp(length) == length
+    }
 }
 
 use bevy::prelude::Component;
@@ -0,0 +1,413 @@
+//! Generating history backwards, from the world as it is to the events that led up to it.
+//!
+//! The world itself isn't changed. Instead, every birth, death, partnership, illness, and founding that the present implies
+//! is given a date before it, and recorded as the simulation steps back past that date.
+//! Backwards runs end at the present, so the calendar's start date is as far back as history goes.
+//! Anything that happened before then is recorded on the start date, when the run steps back to it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use bevy::{ecs::prelude::*, prelude::Parent};
+use rand::Rng;
+use super::{
+    common::Name,
+    defs::{SimulationConfig, species::{AssociatedSpecies, Species}},
+    event::{History, HistoryEvent, HistoryEventKind},
+    living::{Living, afflictions::{Afflicted, Affliction}},
+    person::{Person, kinship::{Parents, Partners}},
+    place::Settlement,
+    rng::SimulationRng,
+    time::Age,
+};
+
+/// How many people a settlement is assumed to be founded by.
+const FOUNDERS: u32 = 10;
+/// How much a settlement's population is assumed to grow each year.
+const GROWTH_PER_YEAR: f64 = 0.02;
+/// The most time between a settlement's founding and its oldest resident's birth.
+const FOUNDING_SLACK: Age = Age::from_years(10);
+
+/// An event in the past, waiting to be recorded.
+#[derive(Debug, Clone)]
+struct PastEvent {
+    /// How many days before the present this happened.
+    before: u32,
+    kind: HistoryEventKind,
+    participants: Vec<Entity>,
+    place: Option<Entity>,
+    summary: String,
+}
+
+/// What's known about a person's life, in days before the present.
+struct Life {
+    name: String,
+    born: u32,
+    died: Option<u32>,
+    /// How old they are, or how old they were when they died.
+    age: Age,
+    /// How many days it took them to grow up.
+    maturity: u32,
+    place: Option<Entity>,
+}
+
+/// Every event the present implies, planned out when a backwards run starts. This isn't saved, and is planned again if it's missing.
+/// It's also planned again whenever the run starts from the beginning, since the world might have been edited since.
+#[derive(Resource, Debug, Default)]
+pub struct Past {
+    /// Most recent first.
+    events: Vec<PastEvent>,
+}
+
+impl Past {
+    /// Works out what must have happened before the world as it is now.
+    pub fn plan(world: &mut World, rng: &mut impl Rng) -> Self {
+        let mut events = vec![];
+
+        let lives = plan_lives(world, rng);
+        record_lives(world, &lives, &mut events);
+        plan_partnerships(world, &lives, rng, &mut events);
+        plan_afflictions(world, &lives, rng, &mut events);
+        plan_settlements(world, &lives, rng, &mut events);
+
+        // Sorting by participants too keeps the order the same no matter how things were planned
+        events.sort_by(|a, b| a.before.cmp(&b.before).then_with(|| a.participants.cmp(&b.participants)).then_with(|| a.kind.cmp(&b.kind)));
+        Self { events }
+    }
+
+    /// Returns the events between `from` and `to` days before the present, most recent first.
+    fn between(&self, from: u32, to: u32) -> impl Iterator<Item = &PastEvent> {
+        self.events.iter().skip_while(move |event| event.before < from).take_while(move |event| event.before < to || to == u32::MAX)
+    }
+}
+
+/// Gives every person a birth date, and a death date if they're dead.
+fn plan_lives(world: &mut World, rng: &mut impl Rng) -> BTreeMap<Entity, Life> {
+    let mut species = world.query::<&Species>();
+    let mut people = world.query_filtered::<(Entity, &Name, &Age, &Living, Option<&AssociatedSpecies>, Option<&Parent>), With<Person>>();
+    let mut lives: BTreeMap<Entity, Life> = people.iter(world).map(|(entity, name, age, _, associated, place)| {
+        let maturity = associated.and_then(|associated| species.get(world, associated.0).ok()).map_or(0, |species| species.maturity_age.days_passed());
+        (entity, Life { name: name.0.clone(), born: age.days_passed(), died: None, age: *age, maturity, place: place.map(|place| place.get()) })
+    }).collect();
+
+    // Children, for the dead, who must have lived until they were born
+    let mut children: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+    let mut parents = world.query::<(Entity, &Parents)>();
+    for (child, parents) in parents.iter(world) {
+        for parent in parents.0.iter() { children.entry(*parent).or_default().push(child); }
+    }
+    for list in children.values_mut() { list.sort(); }
+
+    let mut undated: BTreeSet<Entity> = people.iter(world).filter(|(_, _, _, living, _, _)| **living == Living::Dead).map(|(entity, ..)| entity).collect();
+    let children_of = |entity: &Entity| children.get(entity).map_or(&[][..], |list| list.as_slice());
+
+    // Dead children need dating before their parents, who must have lived until they were born
+    let length = world.resource::<SimulationConfig>().run_length().days_passed().max(1);
+    let mut settle_anyway = false;
+    while !undated.is_empty() {
+        let ready: Vec<Entity> = undated.iter().copied()
+            .filter(|entity| settle_anyway || !children_of(entity).iter().any(|child| undated.contains(child)))
+            .collect();
+
+        // Broken family trees can loop, so stop waiting if nobody can be dated
+        if ready.is_empty() {
+            settle_anyway = true;
+            continue;
+        }
+
+        for entity in ready {
+            undated.remove(&entity);
+            let latest = children_of(&entity).iter().filter_map(|child| lives.get(child)).map(|child| child.born).min().unwrap_or(u32::MAX);
+            let died = rng.gen_range(0..latest.min(length).max(1));
+            let life = lives.get_mut(&entity).unwrap();
+            life.died = Some(died);
+            life.born = died.saturating_add(life.age.days_passed());
+        }
+    }
+
+    lives
+}
+
+/// Records every birth and death.
+fn record_lives(world: &mut World, lives: &BTreeMap<Entity, Life>, events: &mut Vec<PastEvent>) {
+    let mut parents = world.query::<&Parents>();
+    let mut afflicted = world.query::<&Afflicted>();
+    let mut names = world.query::<&Name>();
+    let mut associated = world.query::<&AssociatedSpecies>();
+    let mut species = world.query::<&Species>();
+
+    for (entity, life) in lives.iter() {
+        // Births
+        let mut participants = vec![*entity];
+        if let Ok(parents) = parents.get(world, *entity) { participants.extend(parents.0.iter().copied()); }
+        let parent_names: Vec<&str> = participants[1..].iter().filter_map(|parent| lives.get(parent)).map(|parent| parent.name.as_str()).collect();
+        let summary = match parent_names.as_slice() {
+            [] => format!("{} was born.", life.name),
+            [parent] => format!("{} was born to {}.", life.name, parent),
+            [parents @ .., last] => format!("{} was born to {} and {}.", life.name, parents.join(", "), last),
+        };
+        events.push(PastEvent { before: life.born, kind: HistoryEventKind::Birth, participants, place: life.place, summary });
+
+        // Deaths, blamed on the most severe affliction, or old age if there isn't one
+        let Some(died) = life.died else { continue };
+        let cause = afflicted.get(world, *entity).ok()
+            .and_then(|afflicted| afflicted.iter().filter(|(_, severity)| **severity > 0.0).max_by(|a, b| a.1.total_cmp(b.1)).map(|(affliction, _)| *affliction))
+            .and_then(|affliction| names.get(world, affliction).ok())
+            .map(|name| name.0.clone());
+        let old_age = associated.get(world, *entity).ok()
+            .and_then(|associated| species.get(world, associated.0).ok())
+            .is_some_and(|species| species.aging.harm(life.age, species.max_age) > 0.0);
+
+        let summary = match (cause, old_age) {
+            (Some(cause), _) => format!("{} died of {}.", life.name, cause),
+            (None, true) => format!("{} died of old age.", life.name),
+            (None, false) => format!("{} died.", life.name),
+        };
+        events.push(PastEvent { before: died, kind: HistoryEventKind::Death, participants: vec![*entity], place: life.place, summary });
+    }
+}
+
+/// Dates partnerships after both partners grew up, and before they had children or died.
+fn plan_partnerships(world: &mut World, lives: &BTreeMap<Entity, Life>, rng: &mut impl Rng, events: &mut Vec<PastEvent>) {
+    let mut partners = world.query::<(Entity, &Partners)>();
+    let mut parents = world.query::<(Entity, &Parents)>();
+
+    let mut pairs: Vec<(Entity, Entity)> = partners.iter(world)
+        .flat_map(|(entity, partners)| partners.0.iter().filter(move |partner| entity < **partner).map(move |partner| (entity, *partner)))
+        .collect();
+    pairs.sort();
+
+    for (first, second) in pairs {
+        let (Some(a), Some(b)) = (lives.get(&first), lives.get(&second)) else { continue };
+
+        // Their children's births, and either of their deaths, must come after
+        let children = parents.iter(world)
+            .filter(|(_, parents)| parents.0.contains(&first) && parents.0.contains(&second))
+            .filter_map(|(child, _)| lives.get(&child))
+            .map(|child| child.born);
+        let latest = children.chain(a.died).chain(b.died).max().unwrap_or(0);
+        let earliest = (a.born.saturating_sub(a.maturity)).min(b.born.saturating_sub(b.maturity));
+
+        let before = if latest < earliest { rng.gen_range(latest..=earliest) } else { latest };
+        events.push(PastEvent {
+            before,
+            kind: HistoryEventKind::Partnership,
+            participants: vec![first, second],
+            place: None,
+            summary: format!("{} and {} became partners.", a.name, b.name),
+        });
+    }
+}
+
+/// Dates when afflictions were caught, from how far they've progressed.
+fn plan_afflictions(world: &mut World, lives: &BTreeMap<Entity, Life>, rng: &mut impl Rng, events: &mut Vec<PastEvent>) {
+    let mut afflicted = world.query::<(Entity, &Afflicted)>();
+    let mut afflictions = world.query::<(&Affliction, &Name)>();
+
+    let mut found: Vec<(Entity, Entity, f32)> = afflicted.iter(world)
+        .flat_map(|(entity, afflicted)| afflicted.iter().map(move |(affliction, severity)| (entity, *affliction, *severity)))
+        .filter(|(_, _, severity)| *severity > 0.0)
+        .collect();
+    found.sort_by_key(|(entity, affliction, _)| (*entity, *affliction));
+
+    for (entity, affliction, severity) in found {
+        let (Some(life), Ok((definition, name))) = (lives.get(&entity), afflictions.get(world, affliction)) else { continue };
+
+        // Worked back from how quickly it gets worse, or within the last year if it doesn't
+        let rate = definition.progression_speed.effect(false, severity);
+        let duration = match rate > 0.0 {
+            true => (severity / rate) as u32,
+            false => rng.gen_range(0..=Age::from_years(1).days_passed()),
+        };
+        let since = life.died.unwrap_or(0);
+
+        events.push(PastEvent {
+            before: since.saturating_add(duration).min(life.born),
+            kind: HistoryEventKind::AfflictionContracted,
+            participants: vec![entity, affliction],
+            place: None,
+            summary: format!("{} contracted {}.", life.name, name.0),
+        });
+    }
+}
+
+/// Dates settlements' foundings from their residents and population, and records them growing since.
+fn plan_settlements(world: &mut World, lives: &BTreeMap<Entity, Life>, rng: &mut impl Rng, events: &mut Vec<PastEvent>) {
+    let mut settlements = world.query::<(Entity, &Name, &Settlement, Option<&Parent>)>();
+    let mut names = world.query::<&Name>();
+
+    let mut found: Vec<(Entity, String, u32, Option<Entity>)> = settlements.iter(world)
+        .map(|(entity, name, settlement, parent)| (entity, name.0.clone(), settlement.population, parent.map(|parent| parent.get())))
+        .collect();
+    found.sort_by_key(|(entity, ..)| *entity);
+
+    for (entity, name, population, region) in found {
+        // Older than anyone who lives there, and old enough to have grown this big
+        let oldest = lives.values().filter(|life| life.place == Some(entity)).map(|life| life.born).max().unwrap_or(0);
+        let growth_years = match population > FOUNDERS {
+            true => ((population as f64 / FOUNDERS as f64).ln() / (1.0 + GROWTH_PER_YEAR).ln()) as u32,
+            false => 0,
+        };
+        let founded = oldest.max(Age::from_years(growth_years).days_passed()).saturating_add(rng.gen_range(0..=FOUNDING_SLACK.days_passed()));
+
+        let summary = match region.and_then(|region| names.get(world, region).ok()) {
+            Some(region) => format!("{} was founded in {}.", name, region.0),
+            None => format!("{} was founded.", name),
+        };
+        events.push(PastEvent { before: founded, kind: HistoryEventKind::Founding, participants: vec![entity], place: Some(entity), summary });
+
+        // Each time the population doubled, growing steadily from its founders
+        let mut milestone = population / 2;
+        while milestone > FOUNDERS {
+            let ratio = (population as f64 / milestone as f64).ln() / (population as f64 / FOUNDERS as f64).ln();
+            events.push(PastEvent {
+                before: (founded as f64 * ratio) as u32,
+                kind: HistoryEventKind::PopulationChange,
+                participants: vec![entity],
+                place: Some(entity),
+                summary: format!("{} grew to a population of {}.", name, milestone),
+            });
+            milestone /= 2;
+        }
+    }
+}
+
+/// Records the events from the part of the past this tick steps back over.
+/// The last tick also records everything from before the start, since history doesn't go back any further.
+/// This plans the past first if it hasn't been yet, or if this is the first tick, using the same randomness every time so resuming a run doesn't change it.
+pub(super) fn backwards_system(world: &mut World) {
+    if !world.contains_resource::<Past>() || world.resource::<SimulationConfig>().increments_completed == 0 {
+        let mut rng = world.resource::<SimulationRng>().stream_at("past", 0);
+        let past = Past::plan(world, &mut rng);
+        world.insert_resource(past);
+    }
+
+    let config = world.resource::<SimulationConfig>();
+    let from = config.time_elapsed().days_passed();
+    let to = match config.increments_completed.saturating_add(1) >= config.increments_for_completion {
+        true => u32::MAX,
+        false => from.saturating_add(config.days_per_tick()),
+    };
+    let (length, tick) = (config.run_length().days_passed(), config.increments_completed);
+
+    world.resource_scope(|world, past: Mut<Past>| {
+        let mut history = world.resource_mut::<History>();
+        for event in past.between(from, to) {
+            history.record(HistoryEvent {
+                date: Age::from_days(length.saturating_sub(event.before)),
+                tick,
+                kind: event.kind,
+                participants: event.participants.clone(),
+                place: event.place,
+                summary: event.summary.clone(),
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use crate::world::{
+        common::Name,
+        defs::{SimulationConfig, HistoryDirection},
+        event::{History, HistoryEventKind},
+        living::{Living, afflictions::{Affliction, SeverityVariableValue}},
+        sim::{Simulation, systems_check},
+        testing::{sample_world, named},
+        time::Age,
+    };
+    use super::Past;
+
+    /// Sets the sample world to generate this many years backwards.
+    fn backwards(years: u32) -> Simulation {
+        let mut simulation = sample_world();
+        let mut config = simulation.world().unwrap().resource_mut::<SimulationConfig>();
+        config.direction = HistoryDirection::Backwards;
+        config.set_run_length(Age::from_years(years));
+        simulation
+    }
+
+    /// Runs the simulation until it finishes by itself.
+    fn run_to_end(mut simulation: Simulation) -> Simulation {
+        systems_check(simulation.current_or_err().unwrap());
+        let (simulation, result) = simulation.try_execute();
+        result.unwrap();
+        let (simulation, result) = simulation.join();
+        result.unwrap();
+        simulation
+    }
+
+    #[test]
+    fn plan_dates_lives() {
+        let mut simulation = backwards(200);
+        let world = simulation.world().unwrap();
+        let (p0, p1, p5, plague) = (named(world, "P0"), named(world, "P1"), named(world, "P5"), named(world, "Plague"));
+        world.entity_mut(p5).insert(Living::Dead);
+
+        let past = Past::plan(world, &mut ChaCha8Rng::seed_from_u64(0));
+        let find = |kind: HistoryEventKind, entity| past.events.iter().find(|event| event.kind == kind && event.participants[0] == entity).map(|event| event.before);
+
+        assert_eq!(find(HistoryEventKind::Birth, p1), Some(Age::from_years(25).days_passed()));
+        let died = find(HistoryEventKind::Death, p5).unwrap();
+        assert_eq!(find(HistoryEventKind::Birth, p5), Some(died + Age::from_years(45).days_passed()));
+
+        // Halfway through a plague that gets worse by half a point a day
+        let contracted = past.events.iter().find(|event| event.kind == HistoryEventKind::AfflictionContracted).unwrap();
+        assert_eq!(contracted.participants, vec![p0, plague]);
+        assert_eq!(contracted.before, 2);
+
+        assert!(past.events.windows(2).all(|pair| pair[0].before <= pair[1].before));
+    }
+
+    #[test]
+    fn slow_afflictions_are_caught_after_birth() {
+        let mut simulation = backwards(200);
+        let world = simulation.world().unwrap();
+        let (p0, plague) = (named(world, "P0"), named(world, "Plague"));
+        world.entity_mut(p0).insert(Living::Dead);
+        world.entity_mut(plague).get_mut::<Affliction>().unwrap().progression_speed = SeverityVariableValue::Static(f32::MIN_POSITIVE);
+
+        // Taking longer than can be counted to get this bad, so they must have had it since they were born
+        let past = Past::plan(world, &mut ChaCha8Rng::seed_from_u64(0));
+        let born = past.events.iter().find(|event| event.kind == HistoryEventKind::Birth && event.participants[0] == p0).unwrap().before;
+        let contracted = past.events.iter().find(|event| event.kind == HistoryEventKind::AfflictionContracted).unwrap();
+        assert_eq!(contracted.before, born);
+    }
+
+    #[test]
+    fn edits_are_planned_when_starting_again() {
+        let mut simulation = run_to_end(backwards(50));
+
+        // Rename someone, and start again from the beginning
+        let world = simulation.world().unwrap();
+        let p1 = named(world, "P1");
+        world.entity_mut(p1).insert(Name("Q1".to_string()));
+        world.resource_mut::<SimulationConfig>().increments_completed = 0;
+        let mut simulation = run_to_end(simulation);
+
+        let world = simulation.world().unwrap();
+        assert!(world.resource::<History>().iter().any(|event| event.summary == "Q1 was born."));
+    }
+
+    #[test]
+    fn births_before_the_start_are_recorded() {
+        let mut simulation = backwards(50);
+        let world = simulation.world().unwrap();
+        let p5 = named(world, "P5");
+        world.entity_mut(p5).insert(Age::from_years(70));
+
+        let mut simulation = run_to_end(simulation);
+        let world = simulation.world().unwrap();
+        let config = world.resource::<SimulationConfig>();
+        let last_tick = config.increments_for_completion - 1;
+        let birth = world.resource::<History>().iter()
+            .find(|event| event.kind == HistoryEventKind::Birth && event.participants[0] == p5)
+            .cloned()
+            .expect("the birth should be recorded");
+        assert_eq!(birth.date, Age::ZERO);
+        assert_eq!(birth.tick, last_tick);
+
+        // The town's founding is at least as old as the people who live there, so it can't be lost either
+        assert!(world.resource::<History>().iter().any(|event| event.kind == HistoryEventKind::Founding));
+    }
+}
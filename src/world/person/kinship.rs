@@ -0,0 +1,266 @@
+//! Family relationships between people.
+//!
+//! Parents and children are stored on both sides, so use [LinkParent] and [LinkPartners] to change them
+//! rather than editing the components directly.
+
+use std::collections::{BTreeMap, VecDeque};
+use bevy::{prelude::*, ecs::system::Command};
+
+/// The people this person was born to.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Parents(pub Vec<Entity>);
+
+/// The children of this person. This is kept in sync with [Parents].
+#[derive(Component, Debug, Clone, Default)]
+pub struct Offspring(pub Vec<Entity>);
+
+/// This person's spouses or partners, past and present.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Partners(pub Vec<Entity>);
+
+/// Records `parent` as a parent of `child`.
+/// Nothing happens if `parent` is already descended from `child`, since nobody can be their own ancestor.
+pub struct LinkParent {
+    pub child: Entity,
+    pub parent: Entity,
+}
+
+impl Command for LinkParent {
+    fn write(self, world: &mut World) {
+        if self.child == self.parent { return; }
+        if world.get_entity(self.child).is_none() || world.get_entity(self.parent).is_none() { return; }
+        if descendant_depths(world, self.child).contains_key(&self.parent) { return; }
+        push_unique::<Parents>(world, self.child, self.parent, |c| &mut c.0);
+        push_unique::<Offspring>(world, self.parent, self.child, |c| &mut c.0);
+    }
+}
+
+/// Removes `parent` from the parents of `child`.
+pub struct UnlinkParent {
+    pub child: Entity,
+    pub parent: Entity,
+}
+
+impl Command for UnlinkParent {
+    fn write(self, world: &mut World) {
+        if let Some(mut parents) = world.get_mut::<Parents>(self.child) { parents.0.retain(|e| *e != self.parent); }
+        if let Some(mut offspring) = world.get_mut::<Offspring>(self.parent) { offspring.0.retain(|e| *e != self.child); }
+    }
+}
+
+/// Records two people as partners of each other.
+pub struct LinkPartners(pub Entity, pub Entity);
+
+impl Command for LinkPartners {
+    fn write(self, world: &mut World) {
+        if self.0 == self.1 { return; }
+        if world.get_entity(self.0).is_none() || world.get_entity(self.1).is_none() { return; }
+        push_unique::<Partners>(world, self.0, self.1, |c| &mut c.0);
+        push_unique::<Partners>(world, self.1, self.0, |c| &mut c.0);
+    }
+}
+
+/// Removes two people from each other's partners.
+pub struct UnlinkPartners(pub Entity, pub Entity);
+
+impl Command for UnlinkPartners {
+    fn write(self, world: &mut World) {
+        if let Some(mut partners) = world.get_mut::<Partners>(self.0) { partners.0.retain(|e| *e != self.1); }
+        if let Some(mut partners) = world.get_mut::<Partners>(self.1) { partners.0.retain(|e| *e != self.0); }
+    }
+}
+
+/// Adds `value` to a list component on `entity`, inserting the component if it's missing.
+fn push_unique<T: Component + Default>(
+    world: &mut World,
+    entity: Entity,
+    value: Entity,
+    list: fn(&mut T) -> &mut Vec<Entity>,
+) {
+    let mut entity = world.entity_mut(entity);
+    if !entity.contains::<T>() { entity.insert(T::default()); }
+    let mut component = entity.get_mut::<T>().unwrap();
+    let list = list(&mut component);
+    if !list.contains(&value) { list.push(value); }
+}
+
+/// Returns everyone `entity` is descended from, mapped to how many generations up they are.
+fn ancestor_depths(world: &World, entity: Entity) -> BTreeMap<Entity, u32> {
+    walk(world, entity, |world, entity| world.get::<Parents>(entity).map(|p| p.0.as_slice()))
+}
+
+/// Returns everyone descended from `entity`, mapped to how many generations down they are.
+fn descendant_depths(world: &World, entity: Entity) -> BTreeMap<Entity, u32> {
+    walk(world, entity, |world, entity| world.get::<Offspring>(entity).map(|o| o.0.as_slice()))
+}
+
+/// Breadth-first search, recording the shortest distance to everything found.
+fn walk(
+    world: &World,
+    start: Entity,
+    next: impl for<'a> Fn(&'a World, Entity) -> Option<&'a [Entity]>,
+) -> BTreeMap<Entity, u32> {
+    let mut found: BTreeMap<Entity, u32> = BTreeMap::new();
+    let mut queue: VecDeque<(Entity, u32)> = VecDeque::from([(start, 0)]);
+
+    while let Some((entity, depth)) = queue.pop_front() {
+        for related in next(world, entity).unwrap_or(&[]) {
+            if *related == start || found.contains_key(related) { continue; }
+            found.insert(*related, depth + 1);
+            queue.push_back((*related, depth + 1));
+        }
+    }
+
+    found
+}
+
+/// Returns everyone `entity` is descended from, nearest generations first.
+pub fn ancestors(world: &World, entity: Entity) -> Vec<Entity> {
+    sorted_by_depth(ancestor_depths(world, entity))
+}
+
+/// Returns everyone descended from `entity`, nearest generations first.
+pub fn descendants(world: &World, entity: Entity) -> Vec<Entity> {
+    sorted_by_depth(descendant_depths(world, entity))
+}
+
+fn sorted_by_depth(depths: BTreeMap<Entity, u32>) -> Vec<Entity> {
+    let mut list: Vec<(Entity, u32)> = depths.into_iter().collect();
+    list.sort_by_key(|(entity, depth)| (*depth, *entity));
+    list.into_iter().map(|(entity, _)| entity).collect()
+}
+
+/// How one person is related to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relationship {
+    /// They're the same person.
+    Same,
+    /// They're partners.
+    Partner,
+    /// They're an ancestor, this many generations up. `1` is a parent.
+    Ancestor(u32),
+    /// They're a descendant, this many generations down. `1` is a child.
+    Descendant(u32),
+    /// They share a parent.
+    Sibling,
+    /// They share an ancestor that is `up` generations above the first person and `down` generations above the second.
+    Kin { up: u32, down: u32 },
+    /// They have no known relation.
+    Unrelated,
+}
+
+impl Relationship {
+    /// A description of the relationship, intended for people to read.
+    pub fn describe(&self) -> String {
+        fn greats(count: u32) -> String { "great-".repeat(count as usize) }
+
+        match *self {
+            Relationship::Same => "themselves".to_string(),
+            Relationship::Partner => "partner".to_string(),
+            Relationship::Ancestor(1) => "parent".to_string(),
+            Relationship::Ancestor(n) => format!("{}grandparent", greats(n - 2)),
+            Relationship::Descendant(1) => "child".to_string(),
+            Relationship::Descendant(n) => format!("{}grandchild", greats(n - 2)),
+            Relationship::Sibling => "sibling".to_string(),
+            Relationship::Kin { up, down: 1 } => format!("{}aunt or uncle", greats(up - 2)),
+            Relationship::Kin { up: 1, down } => format!("{}niece or nephew", greats(down - 2)),
+            Relationship::Kin { up, down } => {
+                let degree = up.min(down) - 1;
+                let removed = up.abs_diff(down);
+                match removed {
+                    0 => format!("cousin (degree {})", degree),
+                    _ => format!("cousin (degree {}, {} times removed)", degree, removed),
+                }
+            },
+            Relationship::Unrelated => "unrelated".to_string(),
+        }
+    }
+}
+
+/// Returns what `b` is to `a`.
+pub fn relationship_between(world: &World, a: Entity, b: Entity) -> Relationship {
+    if a == b { return Relationship::Same; }
+
+    if world.get::<Partners>(a).is_some_and(|partners| partners.0.contains(&b)) {
+        return Relationship::Partner;
+    }
+
+    let a_ancestors = ancestor_depths(world, a);
+    if let Some(depth) = a_ancestors.get(&b) { return Relationship::Ancestor(*depth); }
+
+    let b_ancestors = ancestor_depths(world, b);
+    if let Some(depth) = b_ancestors.get(&a) { return Relationship::Descendant(*depth); }
+
+    // The closest shared ancestor decides the relation
+    let closest = a_ancestors.iter()
+        .filter_map(|(ancestor, up)| Some((*up, *b_ancestors.get(ancestor)?)))
+        .min_by_key(|(up, down)| (up + down, *up));
+
+    match closest {
+        Some((1, 1)) => Relationship::Sibling,
+        Some((up, down)) => Relationship::Kin { up, down },
+        None => Relationship::Unrelated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, ecs::system::Command};
+    use super::*;
+
+    /// Spawns `count` people with no relations.
+    fn people(world: &mut World, count: usize) -> Vec<Entity> {
+        (0..count).map(|_| world.spawn_empty().id()).collect()
+    }
+
+    #[test]
+    fn relationships() {
+        let mut world = World::new();
+        // 0 and 1 are partners with children 2 and 3, 4 is the child of 2, and 5 is the grandchild of 3
+        let p = people(&mut world, 7);
+        LinkPartners(p[0], p[1]).write(&mut world);
+        for (child, parent) in [(2, 0), (2, 1), (3, 0), (3, 1), (4, 2), (6, 3), (5, 6)] {
+            LinkParent { child: p[child], parent: p[parent] }.write(&mut world);
+        }
+
+        assert_eq!(relationship_between(&world, p[0], p[0]), Relationship::Same);
+        assert_eq!(relationship_between(&world, p[0], p[1]), Relationship::Partner);
+        assert_eq!(relationship_between(&world, p[2], p[0]), Relationship::Ancestor(1));
+        assert_eq!(relationship_between(&world, p[0], p[5]), Relationship::Descendant(3));
+        assert_eq!(relationship_between(&world, p[2], p[3]), Relationship::Sibling);
+        assert_eq!(relationship_between(&world, p[4], p[3]), Relationship::Kin { up: 2, down: 1 });
+        assert_eq!(relationship_between(&world, p[4], p[5]), Relationship::Kin { up: 2, down: 3 });
+        assert_eq!(relationship_between(&world, p[0], p[4]).describe(), "grandchild");
+        assert_eq!(relationship_between(&world, p[1], p[5]).describe(), "great-grandchild");
+    }
+
+    #[test]
+    fn ancestry_is_nearest_first() {
+        let mut world = World::new();
+        // 0 and 1 are the parents of 2, whose child 3 is the parent of 4
+        let p = people(&mut world, 5);
+        for (child, parent) in [(2, 0), (2, 1), (3, 2), (4, 3)] {
+            LinkParent { child: p[child], parent: p[parent] }.write(&mut world);
+        }
+
+        assert_eq!(ancestors(&world, p[4]), vec![p[3], p[2], p[0], p[1]]);
+        assert_eq!(descendants(&world, p[0]), vec![p[2], p[3], p[4]]);
+        assert_eq!(descendants(&world, p[1]), descendants(&world, p[0]));
+        assert!(ancestors(&world, p[0]).is_empty());
+        assert!(descendants(&world, p[4]).is_empty());
+    }
+
+    #[test]
+    fn nobody_is_their_own_ancestor() {
+        let mut world = World::new();
+        let p = people(&mut world, 3);
+        LinkParent { child: p[1], parent: p[0] }.write(&mut world);
+        LinkParent { child: p[2], parent: p[1] }.write(&mut world);
+
+        LinkParent { child: p[0], parent: p[2] }.write(&mut world);
+        LinkParent { child: p[0], parent: p[0] }.write(&mut world);
+        assert!(world.get::<Parents>(p[0]).is_none());
+        assert!(world.get::<Offspring>(p[2]).is_none());
+        assert_eq!(relationship_between(&world, p[2], p[0]), Relationship::Ancestor(2));
+    }
+}
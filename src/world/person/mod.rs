@@ -1,6 +1,10 @@
 //! A person in history.
 
+pub mod kinship;
+pub mod reproduction;
+
 use bevy::ecs::prelude::*;
+use serde::{Serialize, Deserialize};
 use super::{common::Name, living::{Living, health::CachedHealth}, time::Age};
 
 #[derive(Bundle)]
@@ -14,10 +18,10 @@ pub struct PersonBundle {
 }
 
 /// A marker component for a person in the world.
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 pub struct Person;
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Personality {
     pub selflessness: f32,
     pub aggression: f32,
@@ -0,0 +1,315 @@
+//! People having children.
+
+use std::collections::BTreeMap;
+use bevy::prelude::*;
+use rand::{Rng, seq::SliceRandom};
+use crate::world::{
+    common::Name,
+    defs::{SimulationConfig, species::{AssociatedSpecies, Species}},
+    event::{History, HistoryEvent, HistoryEventKind},
+    living::{Living, health::CachedHealth},
+    rng::SimulationRng,
+    season::Today,
+    time::Age,
+};
+use super::{Person, PersonBundle, Personality, kinship::{LinkParent, LinkPartners, Parents, Partners}};
+
+/// How far a child's personality can stray from the average of their parents.
+const PERSONALITY_VARIANCE: f32 = 0.15;
+
+/// The chance each year that two single adults of the same species become partners.
+const PARTNERING_CHANCE_PER_YEAR: f64 = 0.3;
+
+/// Returns the chance of something with a yearly chance of `per_year` happening in one tick.
+fn chance_per_tick(config: &SimulationConfig, per_year: f64) -> f64 {
+    (per_year * config.days_per_tick() as f64 / 360.0).clamp(0.0, 1.0)
+}
+
+/// Pairs up single adults of the same species as partners.
+/// People with a living partner, and close family, are left out.
+pub(in super::super) fn partnering_system(
+    mut commands: Commands,
+    config: Res<SimulationConfig>,
+    rng: Res<SimulationRng>,
+    mut history: ResMut<History>,
+    people: Query<(Entity, &Age, &Living, &AssociatedSpecies), With<Person>>,
+    family: Query<(&Name, Option<&Parents>, Option<&Partners>)>,
+    species: Query<&Species>,
+) {
+    let mut rng = rng.stream("partnering");
+    let chance = chance_per_tick(&config, PARTNERING_CHANCE_PER_YEAR);
+
+    // Everyone looking for a partner, grouped by species
+    let mut single: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+    for (entity, age, state, associated_species) in people.iter() {
+        if *state == Living::Dead { continue; }
+        let Ok(species) = species.get(associated_species.0) else { continue };
+        if *age < species.maturity_age { continue; }
+        if let Ok((_, _, Some(partners))) = family.get(entity) {
+            let alive = |partner: &Entity| people.get(*partner).is_ok_and(|(_, _, state, _)| *state == Living::Alive);
+            if partners.0.iter().any(alive) { continue; }
+        }
+        single.entry(associated_species.0).or_default().push(entity);
+    }
+
+    // Parents, children, and siblings
+    let parents_of = |entity: Entity| match family.get(entity) {
+        Ok((_, Some(parents), _)) => parents.0.as_slice(),
+        _ => &[],
+    };
+    let close_family = |a: Entity, b: Entity| {
+        let a_parents = parents_of(a);
+        let b_parents = parents_of(b);
+        a_parents.contains(&b) || b_parents.contains(&a) || a_parents.iter().any(|parent| b_parents.contains(parent))
+    };
+
+    for (_, mut candidates) in single {
+        // Query order isn't something to rely on for randomness
+        candidates.sort();
+        candidates.shuffle(&mut rng);
+
+        for pair in candidates.chunks_exact(2) {
+            if !rng.gen_bool(chance) || close_family(pair[0], pair[1]) { continue; }
+
+            commands.add(LinkPartners(pair[0], pair[1]));
+
+            let first = family.get(pair[0]).map(|(name, _, _)| name.0.as_str()).unwrap_or("Someone");
+            let second = family.get(pair[1]).map(|(name, _, _)| name.0.as_str()).unwrap_or("someone");
+            history.record(HistoryEvent {
+                date: config.time_elapsed(),
+                tick: config.increments_completed,
+                kind: HistoryEventKind::Partnership,
+                participants: vec![pair[0], pair[1]],
+                place: None,
+                summary: format!("{} and {} became partners.", first, second),
+            });
+        }
+    }
+}
+
+/// Gives couples of fertile partners a chance to have a child, which changes with the season.
+pub(in super::super) fn reproduction_system(
+    mut commands: Commands,
+    today: Today,
+    rng: Res<SimulationRng>,
+    mut history: ResMut<History>,
+    people: Query<(Entity, &Age, &Living, &AssociatedSpecies, &Partners), With<Person>>,
+    details: Query<(&Name, &Personality, Option<&Parent>)>,
+    species: Query<&Species>,
+) {
+    let mut rng = rng.stream("reproduction");
+
+    // Returns the species of a person if they can have children right now
+    let fertile = |entity: Entity| -> Option<Entity> {
+        let (_, age, living, associated_species, _) = people.get(entity).ok()?;
+        if *living == Living::Dead { return None; }
+        let species = species.get(associated_species.0).ok()?;
+        if *age < species.maturity_age || *age >= species.fertile_until { return None; }
+        Some(associated_species.0)
+    };
+
+    // Every fertile couple, once each
+    let mut couples: Vec<(Entity, Entity, Entity)> = vec![];
+    for (entity, _, _, _, partners) in people.iter() {
+        let Some(species_entity) = fertile(entity) else { continue };
+        for partner in partners.0.iter() {
+            if *partner <= entity || fertile(*partner) != Some(species_entity) { continue; }
+            couples.push((entity, *partner, species_entity));
+        }
+    }
+
+    // Query order isn't something to rely on for randomness
+    couples.sort();
+
+    for (first, second, species_entity) in couples {
+        let fertility = species.get(species_entity).unwrap().fertility as f64 * today.season.growth_modifier as f64;
+        let chance = chance_per_tick(&today.config, fertility);
+        if !rng.gen_bool(chance) { continue; }
+
+        let Ok((first_name, first_personality, place)) = details.get(first) else { continue };
+        let Ok((second_name, second_personality, _)) = details.get(second) else { continue };
+
+        let name = generate_name(&mut rng, surname(&first_name.0));
+        let personality = Personality {
+            selflessness: blend(&mut rng, first_personality.selflessness, second_personality.selflessness),
+            aggression: blend(&mut rng, first_personality.aggression, second_personality.aggression),
+        };
+
+        let summary = format!("{} was born to {} and {}.", name, first_name.0, second_name.0);
+
+        let mut child = commands.spawn((
+            PersonBundle {
+                person: Person,
+                personality,
+                name: Name(name),
+                age: Age::ZERO,
+                state: Living::Alive,
+                health: CachedHealth::new(),
+            },
+            AssociatedSpecies(species_entity),
+        ));
+
+        // Children live where their parents do
+        let place = place.map(|place| place.get());
+        if let Some(place) = place { child.set_parent(place); }
+
+        let child = child.id();
+        commands.add(LinkParent { child, parent: first });
+        commands.add(LinkParent { child, parent: second });
+
+        history.record(HistoryEvent {
+            date: today.config.time_elapsed(),
+            tick: today.config.increments_completed,
+            kind: HistoryEventKind::Birth,
+            participants: vec![child, first, second],
+            place,
+            summary,
+        });
+    }
+}
+
+/// Averages two personality values, with a bit of variation.
+fn blend(rng: &mut impl Rng, a: f32, b: f32) -> f32 {
+    let variation = rng.gen_range(-PERSONALITY_VARIANCE..=PERSONALITY_VARIANCE);
+    ((a + b) / 2.0 + variation).clamp(0.0, 1.0)
+}
+
+/// Returns the last word of a name, if it has more than one.
+fn surname(name: &str) -> Option<&str> {
+    let mut words = name.split_whitespace();
+    let first = words.next();
+    let last = words.last();
+    first.and(last)
+}
+
+/// Strings syllables together to make a given name.
+fn generate_name(rng: &mut impl Rng, surname: Option<&str>) -> String {
+    const SYLLABLES: &[&str] = &[
+        "al", "bar", "cel", "dor", "el", "fen", "gar", "hal", "is", "jor", "ka", "lin",
+        "mar", "nor", "os", "pel", "quin", "ros", "sa", "tor", "ul", "ven", "wil", "yr",
+    ];
+
+    let mut given = String::new();
+    for _ in 0..rng.gen_range(2..=3) {
+        given.push_str(SYLLABLES.choose(rng).unwrap());
+    }
+
+    // Capitalise the first letter
+    let mut chars = given.chars();
+    let given = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => given,
+    };
+
+    match surname {
+        Some(surname) => format!("{} {}", given, surname),
+        None => given,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, ecs::system::Command};
+    use crate::world::{
+        defs::{SimulationConfig, Timespan, species::{AssociatedSpecies, Species}},
+        living::Living,
+        person::{Person, kinship::{LinkPartners, Offspring, Parents, Partners}},
+        rng::SimulationRng,
+        season::CurrentSeason,
+        sim::Simulation,
+        testing::{sample_world, named},
+        time::Age,
+    };
+    use super::{partnering_system, reproduction_system};
+
+    /// Sets up the sample world to run one system, with partners linked between the named people.
+    fn world_with<M>(system: impl IntoSystemAppConfig<M>, couples: &[(&str, &str)]) -> Simulation {
+        let mut simulation = sample_world();
+        let app = &mut simulation.current_or_err().unwrap().app;
+        app.insert_resource(SimulationRng::new(0));
+        app.init_resource::<CurrentSeason>();
+        app.add_system(system);
+
+        for (first, second) in couples {
+            let (first, second) = (named(&mut app.world, first), named(&mut app.world, second));
+            LinkPartners(first, second).write(&mut app.world);
+        }
+        simulation
+    }
+
+    /// Runs `ticks` ticks, with fresh randomness for each.
+    fn run(simulation: &mut Simulation, ticks: u32) {
+        let app = &mut simulation.current_or_err().unwrap().app;
+        for tick in 0..ticks {
+            app.world.resource_mut::<SimulationRng>().set_tick(tick);
+            app.update();
+            app.world.resource_mut::<SimulationConfig>().increments_completed += 1;
+        }
+    }
+
+    /// Returns everyone with the given parent.
+    fn children_of(world: &mut World, parent: Entity) -> Vec<Entity> {
+        world.get::<Offspring>(parent).map_or(vec![], |offspring| offspring.0.clone())
+    }
+
+    #[test]
+    fn births_need_fertile_parents() {
+        // P0 and P1 are young enough, P2 and P3 are too old, and P5 is too young
+        let mut simulation = world_with(reproduction_system, &[("P0", "P1"), ("P2", "P3"), ("P4", "P5")]);
+        let world = simulation.world().unwrap();
+        let (p0, p2, p4, p5) = (named(world, "P0"), named(world, "P2"), named(world, "P4"), named(world, "P5"));
+        world.entity_mut(p4).insert(Age::from_years(20));
+        world.entity_mut(p5).insert(Age::from_years(10));
+        let mut species = world.query::<&mut Species>();
+        for mut species in species.iter_mut(world) {
+            species.fertility = 1000.0;
+            species.fertile_until = Age::from_years(30);
+        }
+
+        run(&mut simulation, 3);
+        let world = simulation.world().unwrap();
+        assert_eq!(children_of(world, p0).len(), 3);
+        assert!(children_of(world, p2).is_empty());
+        assert!(children_of(world, p4).is_empty());
+    }
+
+    #[test]
+    fn newborns_are_linked_to_their_parents() {
+        let mut simulation = world_with(reproduction_system, &[("P0", "P1")]);
+        let world = simulation.world().unwrap();
+        let (p0, p1, town) = (named(world, "P0"), named(world, "P1"), named(world, "Town"));
+        world.entity_mut(town).push_children(&[p0]);
+        let mut species = world.query::<&mut Species>();
+        for mut species in species.iter_mut(world) { species.fertility = 1000.0; }
+
+        run(&mut simulation, 1);
+        let world = simulation.world().unwrap();
+        let [child] = children_of(world, p0)[..] else { panic!("there should be one child") };
+        assert_eq!(children_of(world, p1), vec![child]);
+
+        let parents = world.get::<Parents>(child).unwrap();
+        assert!(parents.0.contains(&p0) && parents.0.contains(&p1));
+        assert_eq!(world.get::<Age>(child), Some(&Age::ZERO));
+        assert_eq!(world.get::<Living>(child), Some(&Living::Alive));
+        assert!(world.get::<Person>(child).is_some());
+        assert_eq!(world.get::<AssociatedSpecies>(child).map(|associated| associated.0), world.get::<AssociatedSpecies>(p0).map(|associated| associated.0));
+        assert_eq!(world.get::<Parent>(child).map(|parent| parent.get()), Some(town));
+    }
+
+    #[test]
+    fn partnered_people_are_not_partnered_again() {
+        let mut simulation = world_with(partnering_system, &[("P0", "P1")]);
+        let world = simulation.world().unwrap();
+        world.resource_mut::<SimulationConfig>().timespan = Timespan::Years;
+        let (p0, p1) = (named(world, "P0"), named(world, "P1"));
+
+        run(&mut simulation, 50);
+        let world = simulation.world().unwrap();
+        assert_eq!(world.get::<Partners>(p0).unwrap().0, vec![p1]);
+        assert_eq!(world.get::<Partners>(p1).unwrap().0, vec![p0]);
+
+        // Everyone else was single, and has found someone by now
+        let mut people = world.query_filtered::<Option<&Partners>, With<Person>>();
+        assert!(people.iter(world).all(|partners| partners.is_some_and(|partners| partners.0.len() == 1)));
+    }
+}
@@ -1,7 +1,9 @@
 //! Places in history.
 
-use bevy::ecs::prelude::*;
-use super::common::Name;
+use std::collections::BTreeMap;
+use bevy::{ecs::prelude::*, prelude::Parent};
+use serde::{Serialize, Deserialize};
+use super::{common::Name, defs::SimulationConfig, event::{History, HistoryEvent, HistoryEventKind}, living::Living, person::Person};
 
 /// A bundle for creating regions.
 /// Parent regions must be added manually.
@@ -22,7 +24,7 @@ impl Default for RegionBundle {
 
 /// A general location, like a territory, country, or continent.
 /// This is combined with Bevy parenting to define hierarchies.
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 pub struct Region;
 
 /// A bundle for creating settlements/towns/cities.
@@ -43,7 +45,7 @@ impl Default for SettlementBundle {
 
 /// A discrete settlement, town, or city.
 /// Put this on an entity that is a child of an entity with a `Region` component to start defining nations.
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Settlement {
     pub population: u32,
 }
@@ -54,4 +56,101 @@ impl Default for Settlement {
             population: 0
         }
     }
-}
\ No newline at end of file
+}
+
+/// Counts the living people directly inside each place.
+/// This is how many people actually live in a settlement, since [Settlement::population] only changes when it's edited.
+pub fn living_residents(world: &mut World) -> BTreeMap<Entity, u32> {
+    let mut people = world.query_filtered::<(&Living, &Parent), With<Person>>();
+    let mut residents: BTreeMap<Entity, u32> = BTreeMap::new();
+    for (living, parent) in people.iter(world) {
+        if *living == Living::Alive { *residents.entry(parent.get()).or_default() += 1; }
+    }
+    residents
+}
+
+/// Remembers the last known value of something for every entity, for change-recording systems.
+pub(super) struct LastKnown<T> {
+    initialised: bool,
+    values: BTreeMap<Entity, T>,
+}
+
+impl<T> Default for LastKnown<T> {
+    fn default() -> Self {
+        Self {
+            initialised: false,
+            values: BTreeMap::new(),
+        }
+    }
+}
+
+/// Records changes in settlement population in history.
+pub(super) fn population_history_system(
+    config: Res<SimulationConfig>,
+    mut history: ResMut<History>,
+    mut known: Local<LastKnown<u32>>,
+    settlements: Query<(Entity, &Name, &Settlement), Changed<Settlement>>,
+) {
+    let initialised = known.initialised;
+    known.initialised = true;
+
+    for (entity, name, settlement) in settlements.iter() {
+        let previous = known.values.insert(entity, settlement.population);
+        if !initialised { continue; }
+        let Some(previous) = previous else { continue };
+        if previous == settlement.population { continue; }
+
+        history.record(HistoryEvent {
+            date: config.time_elapsed(),
+            tick: config.increments_completed,
+            kind: HistoryEventKind::PopulationChange,
+            participants: vec![entity],
+            place: Some(entity),
+            summary: format!("The population of {} changed from {} to {}.", name.0, previous, settlement.population),
+        });
+    }
+}
+
+/// Records things being moved around the hierarchy in history.
+pub(super) fn hierarchy_history_system(
+    config: Res<SimulationConfig>,
+    mut history: ResMut<History>,
+    mut known: Local<LastKnown<Entity>>,
+    changed: Query<(Entity, &Parent), Changed<Parent>>,
+    mut removed: RemovedComponents<Parent>,
+    names: Query<&Name>,
+) {
+    let initialised = known.initialised;
+    known.initialised = true;
+
+    let name_of = |entity: Entity| names.get(entity).map(|name| name.0.clone()).unwrap_or(format!("{:?}", entity));
+
+    for (entity, parent) in changed.iter() {
+        let previous = known.values.insert(entity, parent.get());
+        if !initialised || previous == Some(parent.get()) { continue; }
+
+        history.record(HistoryEvent {
+            date: config.time_elapsed(),
+            tick: config.increments_completed,
+            kind: HistoryEventKind::HierarchyChange,
+            participants: vec![entity, parent.get()],
+            place: Some(parent.get()),
+            summary: format!("{} became part of {}.", name_of(entity), name_of(parent.get())),
+        });
+    }
+
+    for entity in removed.iter() {
+        let Some(previous) = known.values.remove(&entity) else { continue };
+        // Despawned entities lose their parent too, but that isn't worth recording
+        if names.get(entity).is_err() { continue; }
+
+        history.record(HistoryEvent {
+            date: config.time_elapsed(),
+            tick: config.increments_completed,
+            kind: HistoryEventKind::HierarchyChange,
+            participants: vec![entity, previous],
+            place: Some(previous),
+            summary: format!("{} stopped being part of {}.", name_of(entity), name_of(previous)),
+        });
+    }
+}
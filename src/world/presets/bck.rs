@@ -0,0 +1,12 @@
+use bevy::prelude::{App, IntoSystemConfigs};
+use crate::world::living::health::health_caching_system;
+use crate::world::past::backwards_system;
+
+/// Adds the systems for generating history backwards from the present, for every timespan.
+pub fn add_backward_presets(app: &mut App) {
+    // Health is cached first so the present is read the same way a forwards run would see it
+    app.add_systems((
+        health_caching_system,
+        backwards_system,
+    ).chain());
+}
\ No newline at end of file
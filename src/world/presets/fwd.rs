@@ -0,0 +1,26 @@
+use bevy::prelude::{App, IntoSystemConfigs};
+use crate::world::common::age_incrementor_system;
+use crate::world::living::afflictions::{affliction_progress_system, affliction_contraction_system};
+use crate::world::living::health::{health_caching_system, death_system};
+use crate::world::place::{population_history_system, hierarchy_history_system};
+use crate::world::person::reproduction::{partnering_system, reproduction_system};
+use crate::world::season::{CurrentSeason, season_system};
+
+/// Adds the systems for generating history forwards. Every timespan uses the same systems, which read how long each tick is from the config.
+pub fn add_forward_presets(app: &mut App) {
+    app.init_resource::<CurrentSeason>();
+
+    // Chained so the systems always run in the same order, keeping the simulation deterministic
+    app.add_systems((
+        season_system,
+        age_incrementor_system,
+        health_caching_system,
+        affliction_progress_system,
+        death_system,
+        partnering_system,
+        reproduction_system,
+        affliction_contraction_system,
+        population_history_system,
+        hierarchy_history_system,
+    ).chain());
+}
\ No newline at end of file
@@ -1,4 +1,2 @@
-pub mod fwd_mon;
-pub mod fwd_day;
-pub mod bck_mon;
-pub mod bck_day;
\ No newline at end of file
+pub mod fwd;
+pub mod bck;
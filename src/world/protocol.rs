@@ -0,0 +1,193 @@
+//! Messages passed between a [Simulation](super::sim::Simulation) and its thread while it's executing.
+
+use std::{collections::VecDeque, time::{Duration, Instant}};
+use super::{calendar::Calendar, event::HistoryEvent, sim::{Checkpoint, MAX_CHECKPOINTS, RECORD_LENGTH}, snapshot::WorldSnapshot, stop::StopCondition, time::Age, stats::{Sample, Statistics, StatisticsConfig}};
+
+/// Sent from the UI to the simulation thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationCommand {
+    /// Stop before the next tick.
+    Stop,
+    /// Wait between ticks until resumed.
+    Pause,
+    /// Stop waiting between ticks.
+    Resume,
+    /// Run this many more ticks while paused.
+    Step(u32),
+    /// The most ticks to run each second, or `None` for no limit.
+    SetSpeedLimit(Option<f32>),
+    /// Publish a [WorldSnapshot] as soon as possible.
+    RequestSnapshot,
+    /// Change which statistics are collected, and how often.
+    ConfigureStatistics(StatisticsConfig),
+}
+
+/// Sent from the simulation thread to the UI.
+#[derive(Debug)]
+pub enum Telemetry {
+    /// A tick finished.
+    Progress {
+        steps_complete: u32,
+        steps_total: u32,
+        /// How long the tick took, including any wait for the speed limit.
+        tick_time: Duration,
+    },
+    /// Statistics sampled after this many ticks were completed.
+    Samples {
+        tick: u32,
+        date: Age,
+        samples: Vec<Sample>,
+    },
+    /// History recorded during the tick that just finished.
+    Events(Vec<HistoryEvent>),
+    /// The simulation started or stopped waiting between ticks.
+    Paused(bool),
+    Snapshot(WorldSnapshot),
+    /// A checkpoint, to be kept by the [Simulation](super::sim::Simulation) when it's frozen.
+    Checkpoint(Checkpoint),
+    /// A stop condition was met. The thread stops after sending this.
+    Stopped(StopCondition),
+    /// A tick failed. The thread stops after sending this.
+    Error(String),
+    /// The thread has stopped, and can be joined.
+    Exited,
+}
+
+/// A fixed-size queue that forgets its oldest value when it's full.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    values: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    /// Iterates from oldest to newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.values.iter()
+    }
+}
+
+/// Everything the UI knows about an executing simulation, built up from [Telemetry].
+/// This is owned by the [Simulation](super::sim::Simulation), and isn't shared with its thread.
+#[derive(Debug)]
+pub struct SimulationBoundary {
+    /// If `true` the simulation has exited. This might be because it's finished, or because an error occurred.
+    pub simulation_exited: bool,
+    /// If `true`, the simulation is waiting between ticks.
+    pub paused: bool,
+    /// Why the simulation failed, if it did.
+    pub error: Option<String>,
+    /// The condition that stopped the simulation early, if one did.
+    pub stopped_by: Option<StopCondition>,
+
+    // Completion measurement
+    pub steps_complete: u32,
+    pub steps_total: u32,
+    /// How long every tick this run took together, and how many there were.
+    tick_time_total: Duration,
+    ticks_measured: u32,
+
+    /// Every statistic sampled this run. These are added to the [Simulation](super::sim::Simulation)'s when it's frozen.
+    pub statistics: Statistics,
+    /// The most recently recorded history, oldest first.
+    pub recent_events: RingBuffer<HistoryEvent>,
+
+    /// Checkpoints taken this run. These are moved into the [Simulation](super::sim::Simulation) when it's frozen.
+    pub checkpoints: Vec<Checkpoint>,
+    /// The most recent read-only copy of the world.
+    pub snapshot: Option<WorldSnapshot>,
+    /// The world's calendar, for showing dates.
+    pub calendar: Calendar,
+    /// When a snapshot was last asked for, if it hasn't arrived yet.
+    pub(super) snapshot_requested: Option<Instant>,
+}
+
+impl SimulationBoundary {
+    pub(super) fn new(steps_complete: u32, steps_total: u32, calendar: Calendar) -> Self {
+        Self {
+            steps_complete,
+            steps_total,
+            calendar,
+            ..Default::default()
+        }
+    }
+
+    /// Updates what's known about the simulation from a message sent by its thread.
+    pub(super) fn receive(&mut self, telemetry: Telemetry) {
+        match telemetry {
+            Telemetry::Progress { steps_complete, steps_total, tick_time } => {
+                self.steps_complete = steps_complete;
+                self.steps_total = steps_total;
+                self.tick_time_total += tick_time;
+                self.ticks_measured += 1;
+            },
+            Telemetry::Samples { tick, date, samples } => self.statistics.record(tick, date, samples),
+            Telemetry::Events(events) => {
+                for event in events { self.recent_events.push(event); }
+            },
+            Telemetry::Paused(paused) => self.paused = paused,
+            Telemetry::Snapshot(snapshot) => {
+                self.snapshot = Some(snapshot);
+                self.snapshot_requested = None;
+            },
+            Telemetry::Checkpoint(checkpoint) => {
+                self.checkpoints.push(checkpoint);
+                if self.checkpoints.len() > MAX_CHECKPOINTS { self.checkpoints.remove(0); }
+            },
+            Telemetry::Stopped(condition) => self.stopped_by = Some(condition),
+            Telemetry::Error(message) => self.error = Some(message),
+            Telemetry::Exited => self.simulation_exited = true,
+        }
+    }
+
+    /// Returns how long each tick has taken on average this run, if any have finished.
+    pub fn average_tick_time(&self) -> Option<Duration> {
+        match self.ticks_measured {
+            0 => None,
+            ticks => Some(self.tick_time_total / ticks),
+        }
+    }
+
+    /// Estimates how long the rest of the run will take, from how long ticks have taken so far.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.average_tick_time().map(|tick_time| tick_time * self.steps_total.saturating_sub(self.steps_complete))
+    }
+}
+
+impl Default for SimulationBoundary {
+    fn default() -> Self {
+        Self {
+            simulation_exited: false,
+            paused: false,
+            error: None,
+            stopped_by: None,
+
+            steps_complete: 0,
+            steps_total: 0,
+            tick_time_total: Duration::ZERO,
+            ticks_measured: 0,
+
+            statistics: Statistics::default(),
+            recent_events: RingBuffer::new(RECORD_LENGTH),
+
+            checkpoints: vec![],
+            snapshot: None,
+            calendar: Calendar::default(),
+            snapshot_requested: None,
+        }
+    }
+}
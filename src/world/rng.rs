@@ -0,0 +1,53 @@
+//! Seeded randomness for the simulation.
+
+use bevy::ecs::system::Resource;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Hands out random number generators derived from [SimulationConfig::seed](super::defs::SimulationConfig::seed).
+///
+/// Systems ask for their own named stream with [SimulationRng::stream], which is reseeded from the seed and the current tick.
+/// This means the numbers a system gets don't depend on what other systems did before it, or what order they ran in.
+/// Systems should still iterate entities in a fixed order (like sorting by `Entity`) before drawing numbers.
+#[derive(Resource, Debug, Clone)]
+pub struct SimulationRng {
+    seed: u32,
+    tick: u32,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u32) -> Self {
+        Self { seed, tick: 0 }
+    }
+
+    /// Sets the tick the streams are derived from. This should be written by the simulation only.
+    pub fn set_tick(&mut self, tick: u32) {
+        self.tick = tick;
+    }
+
+    /// Returns the random number generator for `name` on the current tick.
+    /// Calling this twice in the same tick returns identical generators.
+    pub fn stream(&self, name: &str) -> ChaCha8Rng {
+        self.stream_at(name, self.tick)
+    }
+
+    /// Returns the random number generator for `name` on any tick.
+    /// This is for work that must come out the same no matter which tick it happens on.
+    pub fn stream_at(&self, name: &str, tick: u32) -> ChaCha8Rng {
+        let mut seed = [0u8; 32];
+        seed[0..4].copy_from_slice(&self.seed.to_le_bytes());
+        seed[4..8].copy_from_slice(&tick.to_le_bytes());
+
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(stream_id(name));
+        rng
+    }
+}
+
+/// FNV-1a, used because the standard library's hasher isn't guaranteed to be stable between Rust versions.
+fn stream_id(name: &str) -> u64 {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    name.bytes().fold(OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
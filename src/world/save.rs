@@ -0,0 +1,366 @@
+//! Writing worlds to disk and reading them back.
+//!
+//! Entity IDs aren't stable between runs, so every saved entity is given a file-local ID,
+//! and any components that reference other entities are rewritten to use those IDs instead.
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+use bevy::{ecs::{world::World, prelude::Entity}, prelude::{App, BuildWorldChildren, Children}};
+use serde::{Serialize, Deserialize};
+use super::{
+    calendar::Calendar,
+    common::{Important, Name},
+    defs::{SimulationConfig, species::{AssociatedSpecies, Species}},
+    event::{History, HistoryEvent, HistoryEventKind},
+    living::{Living, afflictions::{Afflicted, Affliction}, health::CachedHealth},
+    person::{Person, Personality, kinship::{Parents, Partners, Offspring}},
+    place::{Region, Settlement},
+    season::Seasons,
+    sim::base_app,
+    stop::StopConditions,
+    time::Age,
+};
+
+/// The version of the world file format. Files with a higher version than this can't be read.
+pub const WORLD_FILE_VERSION: u32 = 1;
+
+/// A world in a form that can be written to disk.
+#[derive(Serialize, Deserialize)]
+pub struct WorldFile {
+    pub version: u32,
+    pub config: SimulationConfig,
+    pub entities: Vec<EntityRecord>,
+    #[serde(default)]
+    pub history: Vec<HistoryEventRecord>,
+    #[serde(default)]
+    pub calendar: Calendar,
+    #[serde(default)]
+    pub seasons: Seasons,
+    #[serde(default)]
+    pub stop_conditions: StopConditions,
+}
+
+/// All the saved components of a single entity.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntityRecord {
+    /// The file-local ID of this entity.
+    pub id: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<Name>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub important: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age: Option<Age>,
+
+    #[serde(skip_serializing_if = "is_false")]
+    pub person: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub personality: Option<Personality>,
+    /// File-local IDs of the people this person was born to.
+    /// The other side of this relationship isn't saved, as it's rebuilt when loading.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parents: Vec<u32>,
+    /// File-local IDs of this person's partners.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub partners: Vec<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub living: Option<Living>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<CachedHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub afflicted: Option<BTreeMap<u32, f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub affliction: Option<Affliction>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub species: Option<Species>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub associated_species: Option<u32>,
+
+    #[serde(skip_serializing_if = "is_false")]
+    pub region: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement: Option<Settlement>,
+
+    /// File-local IDs of this entity's children, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<u32>,
+}
+
+/// A [HistoryEvent] with its entities replaced by file-local IDs.
+/// Participants that no longer exist are left out.
+#[derive(Serialize, Deserialize)]
+pub struct HistoryEventRecord {
+    pub date: Age,
+    #[serde(default)]
+    pub tick: u32,
+    pub kind: HistoryEventKind,
+    pub participants: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place: Option<u32>,
+    pub summary: String,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Error type for reading and writing world files.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The file couldn't be read or written.
+    Io(io::Error),
+
+    /// The world couldn't be turned into text.
+    Serialize(ron::Error),
+
+    /// The file couldn't be parsed.
+    Deserialize(ron::error::SpannedError),
+
+    /// The file was made by a newer version of the program.
+    UnsupportedVersion(u32),
+
+    /// The file references an entity ID that it doesn't contain.
+    MissingEntity(u32),
+
+    /// The world has no `SimulationConfig` resource.
+    MissingConfig,
+}
+
+impl WorldFile {
+    /// Copies everything that can be saved out of the world.
+    pub fn capture(world: &mut World) -> Result<Self, SaveError> {
+        let config = world.get_resource::<SimulationConfig>().ok_or(SaveError::MissingConfig)?.clone();
+
+        // Sorting keeps the output identical for identical worlds
+        let mut entities: Vec<Entity> = world.iter_entities().map(|entity| entity.id()).collect();
+        entities.sort();
+
+        let ids: BTreeMap<Entity, u32> = entities.iter()
+            .enumerate()
+            .map(|(idx, entity)| (*entity, idx as u32))
+            .collect();
+        let id_of = |entity: &Entity| ids.get(entity).copied();
+
+        let mut records = Vec::with_capacity(entities.len());
+        for entity in entities {
+            records.push(EntityRecord {
+                id: ids[&entity],
+
+                name: world.get::<Name>(entity).cloned(),
+                important: world.get::<Important>(entity).is_some(),
+                age: world.get::<Age>(entity).copied(),
+
+                person: world.get::<Person>(entity).is_some(),
+                personality: world.get::<Personality>(entity).cloned(),
+                parents: world.get::<Parents>(entity)
+                    .map(|parents| parents.0.iter().filter_map(id_of).collect())
+                    .unwrap_or_default(),
+                partners: world.get::<Partners>(entity)
+                    .map(|partners| partners.0.iter().filter_map(id_of).collect())
+                    .unwrap_or_default(),
+
+                living: world.get::<Living>(entity).cloned(),
+                health: world.get::<CachedHealth>(entity).cloned(),
+                afflicted: world.get::<Afflicted>(entity).map(|afflicted| {
+                    afflicted.iter().filter_map(|(k, v)| Some((id_of(k)?, *v))).collect()
+                }),
+                affliction: world.get::<Affliction>(entity).map(Affliction::to_saveable),
+
+                species: world.get::<Species>(entity).cloned(),
+                associated_species: world.get::<AssociatedSpecies>(entity).and_then(|species| id_of(&species.0)),
+
+                region: world.get::<Region>(entity).is_some(),
+                settlement: world.get::<Settlement>(entity).cloned(),
+
+                children: world.get::<Children>(entity)
+                    .map(|children| children.iter().filter_map(id_of).collect())
+                    .unwrap_or_default(),
+            });
+        }
+
+        let history = match world.get_resource::<History>() {
+            Some(history) => history.iter().map(|event| HistoryEventRecord {
+                date: event.date,
+                tick: event.tick,
+                kind: event.kind,
+                participants: event.participants.iter().filter_map(id_of).collect(),
+                place: event.place.as_ref().and_then(id_of),
+                summary: event.summary.clone(),
+            }).collect(),
+            None => vec![],
+        };
+
+        Ok(Self {
+            version: WORLD_FILE_VERSION,
+            config,
+            entities: records,
+            history,
+            calendar: world.get_resource::<Calendar>().cloned().unwrap_or_default(),
+            seasons: world.get_resource::<Seasons>().cloned().unwrap_or_default(),
+            stop_conditions: world.get_resource::<StopConditions>().cloned().unwrap_or_default(),
+        })
+    }
+
+    /// Builds a new `App` containing the saved world. The file is left as it was, so it can be restored again.
+    /// The restored world has no systems, so its config is unlocked, as it would be after being read from disk.
+    pub fn restore(&self) -> Result<App, SaveError> {
+        if self.version > WORLD_FILE_VERSION {
+            return Err(SaveError::UnsupportedVersion(self.version));
+        }
+
+        let mut app = base_app();
+        app.insert_resource(SimulationConfig { locked_in: false, ..self.config.clone() });
+        app.insert_resource(self.calendar.clone());
+        app.insert_resource(self.seasons.clone());
+        app.insert_resource(self.stop_conditions.clone());
+        let world = &mut app.world;
+
+        // Spawn everything first so references can be resolved
+        let mut entities: BTreeMap<u32, Entity> = BTreeMap::new();
+        for record in &self.entities {
+            entities.insert(record.id, world.spawn_empty().id());
+        }
+        let entity_of = |id: &u32| entities.get(id).copied().ok_or(SaveError::MissingEntity(*id));
+
+        let mut offspring: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+
+        for record in self.entities.iter() {
+            let entity = entity_of(&record.id)?;
+
+            let afflicted = match &record.afflicted {
+                Some(map) => {
+                    let mut afflicted = BTreeMap::new();
+                    for (k, v) in map { afflicted.insert(entity_of(k)?, *v); }
+                    Some(Afflicted(afflicted))
+                },
+                None => None,
+            };
+            let associated_species = match record.associated_species {
+                Some(id) => Some(AssociatedSpecies(entity_of(&id)?)),
+                None => None,
+            };
+            let children = record.children.iter().map(entity_of).collect::<Result<Vec<Entity>, SaveError>>()?;
+            let parents = record.parents.iter().map(entity_of).collect::<Result<Vec<Entity>, SaveError>>()?;
+            let partners = record.partners.iter().map(entity_of).collect::<Result<Vec<Entity>, SaveError>>()?;
+            for parent in parents.iter() { offspring.entry(*parent).or_default().push(entity); }
+
+            let mut entity = world.entity_mut(entity);
+            if let Some(name) = &record.name { entity.insert(name.clone()); }
+            if record.important { entity.insert(Important); }
+            if let Some(age) = record.age { entity.insert(age); }
+            if record.person { entity.insert(Person); }
+            if let Some(personality) = &record.personality { entity.insert(personality.clone()); }
+            if !parents.is_empty() { entity.insert(Parents(parents)); }
+            if !partners.is_empty() { entity.insert(Partners(partners)); }
+            if let Some(living) = &record.living { entity.insert(living.clone()); }
+            if let Some(health) = &record.health { entity.insert(health.clone()); }
+            if let Some(afflicted) = afflicted { entity.insert(afflicted); }
+            if let Some(affliction) = &record.affliction { entity.insert(affliction.to_saveable()); }
+            if let Some(species) = &record.species { entity.insert(species.clone()); }
+            if let Some(associated_species) = associated_species { entity.insert(associated_species); }
+            if record.region { entity.insert(Region); }
+            if let Some(settlement) = &record.settlement { entity.insert(settlement.clone()); }
+            if !children.is_empty() { entity.push_children(&children); }
+        }
+
+        for (parent, offspring) in offspring {
+            world.entity_mut(parent).insert(Offspring(offspring));
+        }
+
+        let mut history = History::default();
+        for record in self.history.iter() {
+            history.record(HistoryEvent {
+                date: record.date,
+                tick: record.tick,
+                kind: record.kind,
+                participants: record.participants.iter().map(entity_of).collect::<Result<Vec<Entity>, SaveError>>()?,
+                place: match record.place {
+                    Some(id) => Some(entity_of(&id)?),
+                    None => None,
+                },
+                summary: record.summary.clone(),
+            });
+        }
+        app.insert_resource(history);
+
+        Ok(app)
+    }
+
+    /// Writes the world file to disk as RON.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(SaveError::Serialize)?;
+        fs::write(path, text).map_err(SaveError::Io)
+    }
+
+    /// Reads a world file from disk.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        let text = fs::read_to_string(path).map_err(SaveError::Io)?;
+        ron::from_str(&text).map_err(SaveError::Deserialize)
+    }
+}
+
+/// Saves the world to `path`.
+pub fn save_world(world: &mut World, path: impl AsRef<Path>) -> Result<(), SaveError> {
+    WorldFile::capture(world)?.write(path)
+}
+
+/// Loads a world from `path` into a new `App`.
+pub fn load_world(path: impl AsRef<Path>) -> Result<App, SaveError> {
+    WorldFile::read(path)?.restore()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::{defs::SimulationConfig, living::afflictions::{Affliction, AfflictionBundle, SeverityVariableValue}, common::Name, sim::{systems_check, validate_world}, testing::{sample_world, to_text}};
+    use super::WorldFile;
+
+    #[test]
+    fn round_trip() {
+        let mut sim = sample_world();
+        let text = to_text(sim.world().unwrap());
+
+        let file: WorldFile = ron::from_str(&text).unwrap();
+        let mut app = file.restore().unwrap();
+        assert_eq!(text, to_text(&mut app.world));
+    }
+
+    #[test]
+    fn restored_worlds_are_unlocked() {
+        let mut sim = sample_world();
+        let data = sim.current_or_err().unwrap();
+        systems_check(data);
+        assert!(data.app.world.resource::<SimulationConfig>().locked_in);
+
+        let app = WorldFile::capture(&mut data.app.world).unwrap().restore().unwrap();
+        assert!(!app.world.resource::<SimulationConfig>().locked_in);
+    }
+
+    fn curse(severity: f32) -> f32 {
+        -severity * severity
+    }
+
+    #[test]
+    fn custom_severity_is_warned_about() {
+        let mut sim = sample_world();
+        let world = sim.world().unwrap();
+        world.spawn(AfflictionBundle {
+            name: Name("Curse".into()),
+            affliction: Affliction {
+                flat: SeverityVariableValue::Custom(Box::new(curse as fn(f32) -> f32)),
+                ..Default::default()
+            },
+        });
+
+        let diagnostics = validate_world(world);
+        assert!(diagnostics.iter().any(|diagnostic| diagnostic.message.contains("'Curse' uses a custom function")));
+
+        let file: WorldFile = ron::from_str(&to_text(world)).unwrap();
+        let mut app = file.restore().unwrap();
+        let mut query = app.world.query::<(&Name, &Affliction)>();
+        let (_, curse) = query.iter(&app.world).find(|(name, _)| name.0 == "Curse").unwrap();
+        assert!(matches!(curse.flat, SeverityVariableValue::NoAdjustment));
+    }
+}
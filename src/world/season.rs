@@ -0,0 +1,150 @@
+//! Seasons, which let simulation systems behave differently through the year.
+
+use bevy::ecs::{prelude::*, system::SystemParam};
+use serde::{Serialize, Deserialize};
+use super::{calendar::{Calendar, CalendarDate}, defs::SimulationConfig};
+
+/// A part of the year, starting at the beginning of a month of the [Calendar].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Season {
+    pub name: String,
+    /// The index of the month the season starts in. It lasts until the next season starts.
+    pub first_month: usize,
+    /// Multiplies how quickly afflictions get worse, and divides how quickly they get better.
+    pub affliction_modifier: f32,
+    /// Multiplies the chance of couples having children.
+    pub growth_modifier: f32,
+}
+
+/// The seasons of the year. The order doesn't matter, since they're placed by their first month.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Seasons(pub Vec<Season>);
+
+impl Default for Seasons {
+    fn default() -> Self {
+        // Matches the default calendar, and doesn't change anything until the modifiers are changed
+        let season = |name: &str, first_month: usize| Season {
+            name: name.to_string(),
+            first_month,
+            affliction_modifier: 1.0,
+            growth_modifier: 1.0,
+        };
+
+        Self(vec![
+            season("Spring", 2),
+            season("Summer", 5),
+            season("Autumn", 8),
+            season("Winter", 11),
+        ])
+    }
+}
+
+impl Seasons {
+    /// Returns the season that `month` is in.
+    /// Months before the first season of the year are in the last season, carried over from the year before.
+    pub fn of_month(&self, month: usize) -> Option<&Season> {
+        self.0.iter().filter(|season| season.first_month <= month).max_by_key(|season| season.first_month)
+            .or_else(|| self.0.iter().max_by_key(|season| season.first_month))
+    }
+}
+
+/// The season of the current tick. This is worked out at the start of every tick, and isn't saved.
+/// Ticks can be longer than a season, so the modifiers are averaged over every day the tick covers.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct CurrentSeason {
+    /// The name of the season the tick starts in, or empty if there are no seasons.
+    pub name: String,
+    /// The date of the current tick.
+    pub date: CalendarDate,
+    pub affliction_modifier: f32,
+    pub growth_modifier: f32,
+}
+
+impl Default for CurrentSeason {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            date: CalendarDate::default(),
+            affliction_modifier: 1.0,
+            growth_modifier: 1.0,
+        }
+    }
+}
+
+/// The current time in the world, for systems that change through the year.
+#[derive(SystemParam)]
+pub struct Today<'w> {
+    pub config: Res<'w, SimulationConfig>,
+    pub season: Res<'w, CurrentSeason>,
+}
+
+/// Works out the [CurrentSeason] from the date. This should run before anything that uses it.
+pub(super) fn season_system(
+    config: Res<SimulationConfig>,
+    calendar: Option<Res<Calendar>>,
+    seasons: Option<Res<Seasons>>,
+    mut current: ResMut<CurrentSeason>,
+) {
+    let date = calendar.as_ref().map(|calendar| config.current_date(calendar)).unwrap_or_default();
+    let mut updated = CurrentSeason { date, ..Default::default() };
+
+    if let (Some(calendar), Some(seasons)) = (calendar, seasons) {
+        let days = config.days_per_tick();
+        let start = config.now().days_passed() as i64;
+        let (mut affliction, mut growth, mut counted) = (0.0, 0.0, 0);
+        for day in 0..days as i64 {
+            let Some(season) = seasons.of_month(calendar.date_offset(start + day).month) else { continue };
+            if counted == 0 { updated.name = season.name.clone(); }
+            affliction += season.affliction_modifier;
+            growth += season.growth_modifier;
+            counted += 1;
+        }
+
+        if counted > 0 {
+            updated.affliction_modifier = affliction / counted as f32;
+            updated.growth_modifier = growth / counted as f32;
+        }
+    }
+
+    // Avoid triggering change detection every tick
+    if *current != updated { *current = updated; }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::{defs::Timespan, sim::Simulation};
+    use super::*;
+
+    /// Works out the season for the first tick of a world with no growth in winter.
+    fn first_season(timespan: Timespan) -> CurrentSeason {
+        let mut seasons = Seasons::default();
+        seasons.0.iter_mut().filter(|season| season.name == "Winter").for_each(|season| season.growth_modifier = 0.0);
+
+        let mut simulation = Simulation::default();
+        let app = &mut simulation.current_or_err().unwrap().app;
+        app.world.resource_mut::<SimulationConfig>().timespan = timespan;
+        app.insert_resource(seasons);
+        app.init_resource::<CurrentSeason>();
+        app.add_system(season_system);
+        app.update();
+        app.world.resource::<CurrentSeason>().clone()
+    }
+
+    #[test]
+    fn months_wrap_around_the_year() {
+        let seasons = Seasons::default();
+        assert_eq!(seasons.of_month(0).unwrap().name, "Winter");
+        assert_eq!(seasons.of_month(2).unwrap().name, "Spring");
+        assert_eq!(seasons.of_month(11).unwrap().name, "Winter");
+    }
+
+    #[test]
+    fn long_ticks_average_the_seasons() {
+        let month = first_season(Timespan::Months);
+        assert_eq!((month.name.as_str(), month.growth_modifier), ("Winter", 0.0));
+
+        // Winter is a quarter of the year
+        let year = first_season(Timespan::Years);
+        assert_eq!((year.name.as_str(), year.growth_modifier, year.affliction_modifier), ("Winter", 0.75, 1.0));
+    }
+}
@@ -1,217 +1,635 @@
-use std::{sync::{RwLock, Arc, RwLockReadGuard}, thread::{JoinHandle, self}, time::Instant};
-use bevy::{ecs::{world::World, system::Resource, prelude::Entity, query::With}, prelude::{App, HierarchyPlugin, Or}};
+use std::{sync::mpsc::{self, Sender, Receiver, TryRecvError}, thread::{JoinHandle, self}, time::{Instant, Duration}, panic::{self, AssertUnwindSafe}, any::Any};
+use bevy::{ecs::{world::World, system::Resource, schedule::ExecutorKind}, prelude::{App, HierarchyPlugin, CoreSchedule}};
 use either::Either::{self, Left, Right};
-use crate::world::{defs::SimulationConfig, person::Person, place::{Region, Settlement}};
-use super::defs::{HistoryDirection, Timespan};
+use crate::world::{calendar::Calendar, defs::SimulationConfig, time::Age, branch::{Branch, BranchSummary, DEFAULT_BRANCH_NAME}, diagnostics::{self, Diagnostic}, event::History, rng::SimulationRng, save::WorldFile, season::Seasons, snapshot::WorldSnapshot, stop::{StopCondition, StopConditions}, protocol::{SimulationBoundary, SimulationCommand, Telemetry}, stats::{Statistics, StatisticsConfig}};
+use crate::world::presets::{bck::add_backward_presets, fwd::add_forward_presets};
+use super::{defs::{HistoryDirection, Timespan}, soft_limits::MIN_YEARS_TO_SIMULATE};
 
-pub const MIN_SIM_STEPS: u32 = 10;
 pub const RECORD_LENGTH: usize = 250;
+/// How many checkpoints are kept at once. Once there are more, the oldest are dropped.
+pub const MAX_CHECKPOINTS: usize = 20;
+/// How long to wait for a requested [WorldSnapshot] before asking again.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(500);
 
-pub type Boundary = Arc<RwLock<SimulationBoundary>>;
+/// What the simulation thread gives back. If a tick failed, the world is left partway through it, so only the error is returned.
 pub type SimulationReturn = Result<SimulationData, SimulationError>;
 
 /// The simulation of the world.
 pub struct Simulation {
     state: SimulationState,
+    /// The world as it was before the simulation started executing from the beginning.
+    pre_run: Option<WorldFile>,
+    /// The world as it was when the last run started, if that wasn't the beginning, for rebuilding it if the run fails.
+    run_start: Option<WorldFile>,
+    /// Copies of the world taken during execution, oldest first. At most [MAX_CHECKPOINTS] are kept.
+    checkpoints: Vec<Checkpoint>,
+    /// The name of the branch currently being worked on.
+    branch: String,
+    /// Every other branch, in the order they were put away.
+    branches: Vec<Branch>,
+    /// Which statistics to collect while executing.
+    statistics_config: StatisticsConfig,
+    /// Statistics collected in previous runs.
+    statistics: Statistics,
+    /// How long each tick took on average in the last run, for estimating how long the next will take.
+    tick_time: Option<Duration>,
+    /// The condition that ended the last run early, if one did.
+    stopped_by: Option<StopCondition>,
 }
 
 impl Simulation {
     /// Creates a new frozen simulation from a Bevy `App`
     pub fn new(app: App) -> Self {
         Self {
-            state: SimulationState::Frozen(SimulationData { app })
+            state: SimulationState::Frozen(SimulationData { app }),
+            pre_run: None,
+            run_start: None,
+            checkpoints: vec![],
+            branch: DEFAULT_BRANCH_NAME.to_string(),
+            branches: vec![],
+            statistics_config: StatisticsConfig::default(),
+            statistics: Statistics::default(),
+            tick_time: None,
+            stopped_by: None,
         }
     }
 
     /// Returns a mutable reference to the `SimulationData` or an error if it's not possible.
     pub fn current_or_err(&mut self) -> Result<&mut SimulationData, SimulationError> {
         match self.current() {
-            Ok(value) => {
-                match value {
-                    Left(world) => { return Ok(world); },
-                    Right(_) => { return Err(SimulationError::NotFrozen); },
-                }
-            },
-            Err(error) => { return Err(error); }
+            Left(world) => { return Ok(world); },
+            Right(_) => { return Err(SimulationError::NotFrozen); },
         }
     }
 
-    /// Returns either a mutable reference to the `SimulationData` if the simulation is frozen, a `RwLockReadGuard<SimulationBoundary>` if executing, or a `SimulationError` if the boundary is poisoned.
-    pub fn current(&mut self) -> Result<Either<&mut SimulationData, RwLockReadGuard<SimulationBoundary>>, SimulationError> {
+    /// Returns either a mutable reference to the `SimulationData` if the simulation is frozen, or what's known about it if it's executing.
+    pub fn current(&mut self) -> Either<&mut SimulationData, &SimulationBoundary> {
+        self.poll();
         match &mut self.state {
-            SimulationState::Frozen(ref mut data) => {
-                return Ok(Left(data))
-            },
-            SimulationState::Executing { boundary, thread: _ } => {
-                match boundary.read() {
-                    Ok(boundary) => {
-                        return Ok(Right(boundary))
-                    },
-                    Err(_) => {
-                        return Err(SimulationError::BoundaryPoisoned)
-                    }
-                }
-            },
+            SimulationState::Frozen(ref mut data) => Left(data),
+            SimulationState::Executing { boundary, .. } => Right(boundary),
         }
     }
 
-    /// Begins executing the simulation. Always returns the simulation, and returns the Boundary if successful.
-    /// Returns a `SimulationError` if this simulation is already running.
-    pub fn try_execute(mut self) -> (Self, Result<Boundary, SimulationError>) {
-        let app =
+    /// Returns what's known about the simulation if it's executing.
+    pub fn status(&mut self) -> Option<&SimulationBoundary> {
+        self.current().right()
+    }
+
+    /// Begins executing the simulation. Always returns the simulation.
+    /// Returns a `SimulationError` if this simulation is already running, or has already run every step it was meant to.
+    pub fn try_execute(mut self) -> (Self, Result<(), SimulationError>) {
+        let mut app =
         if let SimulationState::Frozen(simulation_internal) = self.state {
             simulation_internal.app
         } else {
             return (self, Err(SimulationError::AlreadySimulating));
         };
 
-        // Create boundary object
-        let status = Arc::new(RwLock::new(SimulationBoundary::default()));
-        let status_for_thread = status.clone();
-        
+        // Nothing would stop a run that starts past its end
+        let cfg = app.world.resource::<SimulationConfig>();
+        if cfg.increments_completed >= cfg.increments_for_completion {
+            self.state = SimulationState::Frozen(SimulationData { app });
+            return (self, Err(SimulationError::AlreadyFinished));
+        }
+
+        // Keep the starting point so it can be gone back to, unless this is carrying on from part way through
+        // Runs that carry on still keep where they started, so the world can be rebuilt if they fail
+        let current_tick = app.world.resource::<SimulationConfig>().increments_completed;
+        let starting_point = WorldFile::capture(&mut app.world).ok();
+        match self.pre_run.is_none() || current_tick == 0 {
+            true => (self.pre_run, self.run_start) = (starting_point, None),
+            false => self.run_start = starting_point,
+        }
+
+        // Checkpoints after this point are from a history that's about to be rewritten
+        self.checkpoints.retain(|checkpoint| checkpoint.tick <= current_tick);
+        self.statistics.retain_until(current_tick);
+        self.stopped_by = None;
+
+        // Conditions that are already met would stop the run straight away, so they're left out
+        let stop_conditions = app.world.get_resource::<StopConditions>().cloned().unwrap_or_default().pending(&mut app.world);
+
+        // Create channels
+        let cfg = app.world.resource::<SimulationConfig>();
+        let calendar = app.world.get_resource::<Calendar>().cloned().unwrap_or_default();
+        let boundary = SimulationBoundary::new(cfg.increments_completed, cfg.increments_for_completion, calendar);
+        let (commands, commands_for_thread) = mpsc::channel::<SimulationCommand>();
+        let (telemetry_for_thread, telemetry) = mpsc::channel::<Telemetry>();
+        let statistics = self.statistics_config.clone();
+
         // Create execution thread
         let thread: JoinHandle<SimulationReturn> = thread::spawn(move || {
             // Take ownership
-            let status = status_for_thread;
+            let commands = commands_for_thread;
+            let telemetry = telemetry_for_thread;
             let mut app = app;
+            let mut control = ThreadControl { statistics, ..Default::default() };
+
+            // The UI might have gone away, in which case there's nobody to tell
+            let send = |message: Telemetry| { let _ = telemetry.send(message); };
+            let mut events_sent = app.world.get_resource::<History>().map_or(0, |history| history.len());
 
             // Logic loop
             loop {
-                let cfg = app.world.resource::<SimulationConfig>();
+                // Handle every command that's arrived, waiting for more while paused
+                loop {
+                    let command = match control.waiting() {
+                        true => commands.recv().map_err(|_| TryRecvError::Disconnected),
+                        false => commands.try_recv(),
+                    };
+                    match command {
+                        Ok(command) => {
+                            if let Some(message) = control.apply(command) { send(message); }
+                        },
+                        Err(TryRecvError::Empty) => break,
+                        // Nobody is left to freeze the simulation, so stop
+                        Err(TryRecvError::Disconnected) => {
+                            control.stop = true;
+                            break;
+                        },
+                    }
+
+                    if control.snapshot_requested {
+                        control.snapshot_requested = false;
+                        send(Telemetry::Snapshot(WorldSnapshot::capture(&mut app.world)));
+                    }
+                }
 
                 // Check we aren't due to stop
-                if status.read().unwrap().stop_next_tick || (cfg.increments_completed == cfg.increments_for_completion) {
+                let cfg = app.world.resource::<SimulationConfig>();
+                if control.stop || cfg.increments_completed >= cfg.increments_for_completion {
+                    send(Telemetry::Exited);
                     return Ok(SimulationData { app });
                 }
 
+                if control.paused { control.steps_to_advance -= 1; }
+
+                // Derive this tick's random streams
+                let tick = cfg.increments_completed;
+                if let Some(mut rng) = app.world.get_resource_mut::<SimulationRng>() {
+                    rng.set_tick(tick);
+                }
+
                 // Time before the tick happens
                 let now = Instant::now();
 
-                // For testing
-                // thread::sleep(std::time::Duration::from_secs_f32(0.1));
-
                 // Run one tick
-                app.update();
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| app.update())) {
+                    let error = SimulationError::TickPanicked { tick, message: panic_message(payload) };
+                    send(Telemetry::Error(format!("{:?}", error)));
+                    send(Telemetry::Exited);
+                    return Err(error);
+                }
 
                 // Measure how long it took to tick
                 let elapsed = now.elapsed();
 
+                // Slow down to the speed limit, if there is one
+                if let Some(ticks_per_second) = control.ticks_per_second {
+                    let minimum = Duration::from_secs_f32(1.0 / ticks_per_second.max(0.01));
+                    if let Some(remaining) = minimum.checked_sub(elapsed) { thread::sleep(remaining); }
+                }
+                let tick_time = now.elapsed();
+
                 // Increase increment counter by 1
                 let mut cfg = app.world.resource_mut::<SimulationConfig>();
                 cfg.increments_completed += 1;
-                let should_exit = cfg.increments_completed >= cfg.increments_for_completion;
-
-                // Write to boundary
-                let mut status = status.write().unwrap();
-                status.steps_total = cfg.increments_for_completion;
-                status.steps_complete = cfg.increments_completed;
-
-                fn push_to_cap<T>(
-                    cap: usize,
-                    value: &mut Vec<T>,
-                    push: T,
-                ) {
-                    let vlen = value.len();
-                    if vlen == cap {
-                        value.remove(0);
+                let checkpoint_due = cfg.checkpoint_interval != 0 && cfg.increments_completed % cfg.checkpoint_interval == 0;
+                let (tick, date) = (cfg.increments_completed, cfg.now());
+                let (steps_complete, steps_total) = (cfg.increments_completed, cfg.increments_for_completion);
+
+                // Take a checkpoint, which is handed over when the simulation is frozen
+                if checkpoint_due {
+                    if let Ok(file) = WorldFile::capture(&mut app.world) {
+                        send(Telemetry::Checkpoint(Checkpoint { tick, date, file }));
                     }
-                    value.push(push);
                 }
 
-                push_to_cap::<f64>(RECORD_LENGTH, &mut status.tick_time_history, elapsed.as_secs_f64());
-                push_to_cap::<f64>(RECORD_LENGTH, &mut status.entity_count_history, app.world.query::<Entity>().iter(&app.world).len() as f64);
-                push_to_cap::<f64>(RECORD_LENGTH, &mut status.people_count_history, app.world.query_filtered::<Entity, With<Person>>().iter(&app.world).len() as f64);
-                push_to_cap::<f64>(RECORD_LENGTH, &mut status.place_count_history, app.world.query_filtered::<Entity, Or<(With<Region>, With<Settlement>)>>().iter(&app.world).len() as f64);
+                // Report on the tick
+                send(Telemetry::Progress { steps_complete, steps_total, tick_time });
 
-                // Exit if simulation is complete
-                if should_exit {
-                    status.simulation_exited = true;
-                    return Ok(SimulationData { app });
+                // Stop if something the user was waiting for has happened
+                if let Some(condition) = stop_conditions.iter().find(|condition| condition.met(&mut app.world)) {
+                    send(Telemetry::Stopped(condition.clone()));
+                    control.stop = true;
+                }
+                if control.statistics.due(tick) {
+                    let samples = control.statistics.sample(&mut app.world, elapsed.as_secs_f64());
+                    send(Telemetry::Samples { tick, date, samples });
+                }
+
+                if let Some(history) = app.world.get_resource::<History>() {
+                    if history.len() > events_sent {
+                        send(Telemetry::Events(history.iter().skip(events_sent).cloned().collect()));
+                        events_sent = history.len();
+                    }
                 }
             }
         });
 
         // Change simulation state
         self.state = SimulationState::Executing {
-            boundary: status.clone(),
+            boundary,
+            commands,
+            telemetry,
             thread,
         };
 
-        // Return boundary
-        (self, Ok(status))
+        (self, Ok(()))
     }
 
     /// Signals the simulation to stop and blocks until it finishes.
-    /// Returns the frozen simulation if successful, and returns a `SimulationError` if the boundary is poisoned or the simulation panicked.
-    pub fn freeze(mut self) -> Result<Self, SimulationError> {
-        // Check state is executing and not frozen
-        let (boundary, thread) =
-        if let SimulationState::Executing { boundary, thread } = self.state {
-            (boundary, thread)
+    /// Always returns the simulation, which is frozen afterwards. If something went wrong, it holds the best world that could be recovered.
+    pub fn freeze(self) -> (Self, Result<(), SimulationFailure>) {
+        // If this fails, the thread has already stopped
+        let _ = self.send(SimulationCommand::Stop);
+        self.join()
+    }
+
+    /// Pauses the simulation between ticks, without stopping the thread.
+    pub fn pause(&self) -> Result<(), SimulationError> {
+        self.send(SimulationCommand::Pause)
+    }
+
+    /// Resumes a paused simulation.
+    pub fn resume(&self) -> Result<(), SimulationError> {
+        self.send(SimulationCommand::Resume)
+    }
+
+    /// Runs `steps` more ticks while paused.
+    pub fn advance(&self, steps: u32) -> Result<(), SimulationError> {
+        self.send(SimulationCommand::Step(steps))
+    }
+
+    /// Limits how many ticks run each second. `None` runs as fast as possible.
+    pub fn set_speed_limit(&self, ticks_per_second: Option<f32>) -> Result<(), SimulationError> {
+        self.send(SimulationCommand::SetSpeedLimit(ticks_per_second))
+    }
+
+    /// Changes which statistics are collected while executing, and how often.
+    /// This takes effect immediately if the simulation is executing, and otherwise the next time it starts.
+    pub fn configure_statistics(&mut self, config: StatisticsConfig) -> Result<(), SimulationError> {
+        self.statistics_config = config.clone();
+        match self.state {
+            SimulationState::Frozen(_) => Ok(()),
+            SimulationState::Executing { .. } => self.send(SimulationCommand::ConfigureStatistics(config)),
+        }
+    }
+
+    /// Returns the statistics collected in previous runs of this branch.
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// Returns the condition that ended the last run early, if one did.
+    pub fn stopped_by(&self) -> Option<&StopCondition> {
+        self.stopped_by.as_ref()
+    }
+
+    /// Returns how long each tick took on average in the last run, if there's been one.
+    pub fn tick_time(&self) -> Option<Duration> {
+        self.tick_time
+    }
+
+    /// Asks the simulation for a new [WorldSnapshot], unless one was asked for recently and hasn't arrived yet.
+    pub fn request_snapshot(&mut self) -> Result<(), SimulationError> {
+        let SimulationState::Executing { boundary, .. } = &mut self.state else { return Err(SimulationError::NotExecuting); };
+        if boundary.snapshot_requested.is_some_and(|requested| requested.elapsed() < SNAPSHOT_INTERVAL) { return Ok(()); }
+        boundary.snapshot_requested = Some(Instant::now());
+        self.send(SimulationCommand::RequestSnapshot)
+    }
+
+    fn send(&self, command: SimulationCommand) -> Result<(), SimulationError> {
+        match &self.state {
+            SimulationState::Frozen(_) => Err(SimulationError::NotExecuting),
+            SimulationState::Executing { commands, .. } => commands.send(command).map_err(|_| SimulationError::Disconnected),
+        }
+    }
+
+    /// Updates the boundary with everything the simulation thread has sent since last time.
+    fn poll(&mut self) {
+        let SimulationState::Executing { boundary, telemetry, .. } = &mut self.state else { return; };
+        loop {
+            match telemetry.try_recv() {
+                Ok(message) => boundary.receive(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    boundary.simulation_exited = true;
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Returns `true` if the simulation is frozen, or its thread has stopped and it can be joined without blocking.
+    pub fn is_finished(&self) -> bool {
+        match &self.state {
+            SimulationState::Frozen(_) => true,
+            SimulationState::Executing { thread, .. } => thread.is_finished(),
+        }
+    }
+
+    /// Blocks until the simulation finishes by itself, without signalling it to stop.
+    /// Always returns the simulation, which is frozen afterwards. If something went wrong, it holds the best world that could be recovered.
+    pub fn join(self) -> (Self, Result<(), SimulationFailure>) {
+        let Simulation { state, pre_run, run_start, mut checkpoints, branch, branches, statistics_config, mut statistics, mut tick_time, stopped_by } = self;
+        let (mut boundary, telemetry, thread) =
+        if let SimulationState::Executing { boundary, telemetry, thread, .. } = state {
+            (boundary, telemetry, thread)
         } else {
             // Already frozen
-            return Ok(self);
+            return (Simulation { state, pre_run, run_start, checkpoints, branch, branches, statistics_config, statistics, tick_time, stopped_by }, Ok(()));
         };
 
-        // Signal simulation to freeze
-        if let Ok(mut boundary) = boundary.write() {
-            boundary.stop_next_tick = true;
-        } else {
-            return Err(SimulationError::BoundaryPoisoned);
-        }
+        let result = thread.join();
+
+        // Collect checkpoints and statistics from the run
+        for message in telemetry.try_iter() { boundary.receive(message); }
+        checkpoints.append(&mut boundary.checkpoints);
+        checkpoints.drain(..checkpoints.len().saturating_sub(MAX_CHECKPOINTS));
+        tick_time = boundary.average_tick_time().or(tick_time);
+        let stopped_by = boundary.stopped_by.take().or(stopped_by);
+        statistics.merge(boundary.statistics);
 
-        // Wait for thread
-        if let Ok(data) = thread.join() {
-            match data {
-                Ok(data) => {
-                    self.state = SimulationState::Frozen(data);
-                    return Ok(self);
-                
+        let error = match result {
+            Ok(Ok(data)) => {
+                return (Self { state: SimulationState::Frozen(data), pre_run, run_start, checkpoints, branch, branches, statistics_config, statistics, tick_time, stopped_by }, Ok(()));
+            },
+            Ok(Err(error)) => error,
+            Err(payload) => SimulationError::SimulationPanicked(panic_message(payload)),
+        };
+
+        // Rebuild the world from just before the failed tick, or fall back to the latest checkpoint, then the pre-run snapshot, then nothing
+        let latest = checkpoints.last().map(|checkpoint| checkpoint.tick);
+        let mut simulation = Self { state: SimulationState::Frozen(SimulationData { app: base_app() }), pre_run, run_start, checkpoints, branch, branches, statistics_config, statistics, tick_time, stopped_by };
+        let rebuilt = match error {
+            SimulationError::TickPanicked { tick, .. } => simulation.replay_to(tick).map(|data| (tick, data)),
+            _ => None,
+        };
+        let recovered = match rebuilt {
+            Some((tick, data)) => {
+                simulation.state = SimulationState::Frozen(data);
+                Recovered::LastGoodState(tick)
+            },
+            None => match latest.map(|tick| (tick, simulation.restore_checkpoint(tick))) {
+                Some((tick, Ok(()))) => Recovered::Checkpoint(tick),
+                _ => match simulation.restore_pre_run() {
+                    Ok(()) => Recovered::PreRunSnapshot,
+                    Err(_) => {
+                        simulation.state = Simulation::default().state;
+                        Recovered::Nothing
+                    },
                 },
-                Err(error) => { return Err(error) },
+            },
+        };
+
+        (simulation, Err(SimulationFailure { error, recovered }))
+    }
+
+    /// Rebuilds the world as it was after `tick` increments of the last run, by running the same steps again from the latest copy taken during it.
+    /// Runs with the same seed always turn out the same, so this is the world the run had, without a copy being kept of every tick.
+    fn replay_to(&self, tick: u32) -> Option<SimulationData> {
+        let start = self.run_start.as_ref().or(self.pre_run.as_ref())?;
+        let start_tick = start.config.increments_completed;
+        let file = self.checkpoints.iter().rev()
+            .find(|checkpoint| checkpoint.tick > start_tick && checkpoint.tick <= tick)
+            .map_or(start, |checkpoint| &checkpoint.file);
+
+        let mut data = SimulationData { app: file.restore().ok()? };
+        systems_check(&mut data);
+
+        // The same steps the simulation thread takes for each tick, without reporting anything
+        let replayed = panic::catch_unwind(AssertUnwindSafe(|| {
+            while data.app.world.resource::<SimulationConfig>().increments_completed < tick {
+                let completed = data.app.world.resource::<SimulationConfig>().increments_completed;
+                if let Some(mut rng) = data.app.world.get_resource_mut::<SimulationRng>() { rng.set_tick(completed); }
+                data.app.update();
+                data.app.world.resource_mut::<SimulationConfig>().increments_completed += 1;
             }
-        } else {
-            return Err(SimulationError::SimulationPanicked);
+        }));
+        replayed.ok().map(|_| data)
+    }
+
+    /// Returns `true` if there is a snapshot from before the simulation started executing from the beginning.
+    pub fn has_pre_run_snapshot(&self) -> bool {
+        self.pre_run.is_some()
+    }
+
+    /// Returns the snapshot taken before the simulation started executing from the beginning, for writing to disk.
+    pub fn pre_run_snapshot(&self) -> Option<&WorldFile> {
+        self.pre_run.as_ref()
+    }
+
+    /// Replaces the frozen world with the snapshot taken before the simulation started executing from the beginning.
+    /// The restored world has no systems and its config is unlocked, so settings like the seed can be changed before running it again.
+    /// The snapshot is kept, so this can be done more than once.
+    pub fn restore_pre_run(&mut self) -> Result<(), SimulationError> {
+        if let SimulationState::Executing { .. } = self.state {
+            return Err(SimulationError::NotFrozen);
+        }
+
+        let Some(file) = &self.pre_run else { return Err(SimulationError::NoSnapshot); };
+        let app = file.restore().map_err(|_| SimulationError::NoSnapshot)?;
+        self.state = SimulationState::Frozen(SimulationData { app });
+        Ok(())
+    }
+
+    /// Returns the checkpoints taken in previous runs, oldest first.
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// Replaces the frozen world with the checkpoint taken at `tick`.
+    /// Like [Simulation::restore_pre_run], the config is unlocked. Later checkpoints are kept until the simulation runs again.
+    pub fn restore_checkpoint(&mut self, tick: u32) -> Result<(), SimulationError> {
+        if let SimulationState::Executing { .. } = self.state {
+            return Err(SimulationError::NotFrozen);
+        }
+
+        let Some(checkpoint) = self.checkpoints.iter().find(|checkpoint| checkpoint.tick == tick) else { return Err(SimulationError::NoSnapshot); };
+        let app = checkpoint.file.restore().map_err(|_| SimulationError::NoSnapshot)?;
+        self.state = SimulationState::Frozen(SimulationData { app });
+        Ok(())
+    }
+
+    /// Returns the name of the branch currently being worked on.
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// Returns the names of every branch, with the current one last.
+    pub fn branch_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.branches.iter().map(|branch| branch.name.clone()).collect();
+        names.push(self.branch.clone());
+        names
+    }
+
+    /// Starts a new branch called `name` and switches to it.
+    /// The new branch starts from the current branch's pre-run snapshot, or from the current world if it hasn't been run yet.
+    pub fn fork_branch(&mut self, name: &str) -> Result<(), SimulationError> {
+        if name.is_empty() || self.branch_names().iter().any(|existing| existing == name) {
+            return Err(SimulationError::BranchExists(name.to_string()));
+        }
+
+        // The current branch keeps its own copy of the snapshot
+        let mut app = match &self.pre_run {
+            Some(file) => file.restore().map_err(|_| SimulationError::NoSnapshot)?,
+            None => WorldFile::capture(self.world()?).and_then(|file| file.restore()).map_err(|_| SimulationError::NoSnapshot)?,
         };
+        let start = WorldFile::capture(&mut app.world).map_err(|_| SimulationError::NoSnapshot)?;
+
+        // Statistics from before the branch point are shared history
+        let mut statistics = self.statistics.clone();
+        statistics.retain_until(start.config.increments_completed);
+
+        self.stash_branch()?;
+        self.state = SimulationState::Frozen(SimulationData { app });
+        self.pre_run = Some(start);
+        self.statistics = statistics;
+        self.branch = name.to_string();
+        Ok(())
+    }
+
+    /// Puts the current world away and switches to the branch called `name`.
+    pub fn switch_branch(&mut self, name: &str) -> Result<(), SimulationError> {
+        if name == self.branch { return Ok(()); }
+        self.world()?;
+
+        let Some(index) = self.branches.iter().position(|branch| branch.name == name) else {
+            return Err(SimulationError::NoSuchBranch(name.to_string()));
+        };
+
+        // The target branch is only taken out of the list once nothing else can fail
+        let app = self.branches[index].world.restore().map_err(|_| SimulationError::NoSnapshot)?;
+        self.stash_branch()?;
+        let target = self.branches.remove(index);
+
+        self.state = SimulationState::Frozen(SimulationData { app });
+        self.pre_run = target.pre_run;
+        self.checkpoints = target.checkpoints;
+        self.statistics = target.statistics;
+        self.tick_time = target.tick_time;
+        self.stopped_by = target.stopped_by;
+        self.branch = target.name;
+        Ok(())
+    }
+
+    /// Deletes a branch that isn't the current one.
+    pub fn delete_branch(&mut self, name: &str) -> Result<(), SimulationError> {
+        let Some(index) = self.branches.iter().position(|branch| branch.name == name) else {
+            return Err(SimulationError::NoSuchBranch(name.to_string()));
+        };
+        self.branches.remove(index);
+        Ok(())
+    }
+
+    /// Returns numbers about every branch for comparing them, in the same order as [Simulation::branch_names].
+    pub fn branch_summaries(&mut self) -> Result<Vec<BranchSummary>, SimulationError> {
+        let mut summaries: Vec<BranchSummary> = self.branches.iter().map(|branch| BranchSummary::of_file(&branch.name, &branch.world)).collect();
+        let current = WorldFile::capture(self.world()?).map_err(|_| SimulationError::NoSnapshot)?;
+        summaries.push(BranchSummary::of_file(&self.branch, &current));
+        Ok(summaries)
+    }
+
+    /// Moves the current world, snapshot, checkpoints, statistics, and details of the last run into the list of other branches.
+    fn stash_branch(&mut self) -> Result<(), SimulationError> {
+        let world = WorldFile::capture(self.world()?).map_err(|_| SimulationError::NoSnapshot)?;
+        self.branches.push(Branch {
+            name: self.branch.clone(),
+            world,
+            pre_run: self.pre_run.take(),
+            checkpoints: std::mem::take(&mut self.checkpoints),
+            statistics: std::mem::take(&mut self.statistics),
+            tick_time: self.tick_time.take(),
+            stopped_by: self.stopped_by.take(),
+        });
+        Ok(())
     }
 
     pub fn world(&mut self) -> Result<&mut World, SimulationError> {
         match &mut self.state {
             SimulationState::Frozen(ref mut data) => { return Ok(&mut data.app.world) },
-            SimulationState::Executing { boundary: _, thread: _ } => { return Err(SimulationError::NotFrozen) },
+            SimulationState::Executing { .. } => { return Err(SimulationError::NotFrozen) },
         }
     }
 }
 
 impl Default for Simulation {
     fn default() -> Self {
-        let mut app = App::new();
-
-        app.add_plugin(HierarchyPlugin);
+        let mut app = base_app();
 
-        app.insert_resource(SimulationConfig {
+        let mut config = SimulationConfig {
             locked_in: false,
             name: "".to_string(),
             seed: rand::random(),
             direction: HistoryDirection::Forwards,
             timespan: Timespan::Months,
             increments_completed: 0,
-            increments_for_completion: MIN_SIM_STEPS,
-        });
+            increments_for_completion: 0,
+            checkpoint_interval: 100,
+        };
+        config.set_run_length(Age::from_years(MIN_YEARS_TO_SIMULATE));
+        app.insert_resource(config);
+
+        app.init_resource::<History>();
+        app.init_resource::<Calendar>();
+        app.init_resource::<Seasons>();
+        app.init_resource::<StopConditions>();
 
         Self {
-            state: SimulationState::Frozen(SimulationData { app })
+            state: SimulationState::Frozen(SimulationData { app }),
+            pre_run: None,
+            run_start: None,
+            checkpoints: vec![],
+            branch: DEFAULT_BRANCH_NAME.to_string(),
+            branches: vec![],
+            statistics_config: StatisticsConfig::default(),
+            statistics: Statistics::default(),
+            tick_time: None,
+            stopped_by: None,
         }
     }
 }
 
+/// A copy of the world taken while the simulation was executing.
+pub struct Checkpoint {
+    /// How many increments had been completed when this was taken.
+    pub tick: u32,
+    /// How much in-world time had passed when this was taken.
+    pub date: Age,
+    file: WorldFile,
+}
+
+impl std::fmt::Debug for Checkpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Checkpoint").field("tick", &self.tick).field("date", &self.date).finish_non_exhaustive()
+    }
+}
+
+/// Creates an `App` with the plugins the simulation needs, but no resources, systems, or entities.
+pub fn base_app() -> App {
+    let mut app = App::new();
+    app.add_plugin(HierarchyPlugin);
+
+    // Systems run one after another anyway, and running them on the simulation thread means a panic can be caught cleanly
+    app.edit_schedule(CoreSchedule::Main, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+
+    app
+}
+
 pub enum SimulationState {
     /// The simulation is frozen, and mutably accessible.
     Frozen(SimulationData),
 
-    /// The simulation is executing on another thread, and must be communicated with using the boundary.
+    /// The simulation is executing on another thread, and must be communicated with using the channels.
     Executing {
-        boundary: Boundary,
+        /// What's been heard from the thread so far.
+        boundary: SimulationBoundary,
+        commands: Sender<SimulationCommand>,
+        telemetry: Receiver<Telemetry>,
         thread: JoinHandle<SimulationReturn>,
     },
 }
@@ -219,63 +637,328 @@ pub enum SimulationState {
 /// Error type for operations on the `Simulation` object.
 #[derive(Debug)]
 pub enum SimulationError {
-    /// The communications boundary was poisoned.
-    BoundaryPoisoned,
+    /// The simulation thread stopped listening for commands.
+    Disconnected,
     
-    /// The simulation thread panicked somewhere.
-    SimulationPanicked,
+    /// The simulation thread panicked outside of a tick.
+    SimulationPanicked(String),
+
+    /// A system panicked while running a tick.
+    TickPanicked {
+        tick: u32,
+        message: String,
+    },
+
+    /// There's no snapshot to restore.
+    NoSnapshot,
+
+    /// A branch with this name already exists, or the name is empty.
+    BranchExists(String),
+
+    /// There's no branch with this name.
+    NoSuchBranch(String),
 
     /// The simulation is already running.
     AlreadySimulating,
 
+    /// The simulation has already run for as many steps as it was meant to.
+    AlreadyFinished,
+
     /// The simulation isn't frozen when it should be.
     NotFrozen,
+
+    /// The simulation isn't executing when it should be.
+    NotExecuting,
+}
+
+/// Returned when the simulation stops because of an error.
+#[derive(Debug)]
+pub struct SimulationFailure {
+    pub error: SimulationError,
+    /// What the simulation was left holding.
+    pub recovered: Recovered,
+}
+
+/// The world a [Simulation] holds after it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovered {
+    /// The world as it was just before the tick that failed, after this many increments. Nothing from before the failure was lost.
+    LastGoodState(u32),
+    /// The latest checkpoint taken before the failure, which was taken after this many increments.
+    /// This is only used if the world from just before the failure couldn't be rebuilt, and anything simulated after the checkpoint was lost.
+    Checkpoint(u32),
+    /// The world as it was before the simulation started executing. Everything simulated was lost.
+    PreRunSnapshot,
+    /// Nothing could be recovered, and the simulation holds a new world.
+    Nothing,
+}
+
+/// Gets the message out of a panic, if it has one.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() { return message.to_string(); }
+    if let Some(message) = payload.downcast_ref::<String>() { return message.clone(); }
+    "unknown panic".to_string()
 }
 
 pub struct SimulationData {
     pub app: App
 }
 
-#[derive(Debug)]
-/// Allows communication between the GUI and the simulation thread.
-pub struct SimulationBoundary {
-    /// If `true`, the simulation will freeze on the next tick. This should be written by UI only.
-    pub stop_next_tick: bool,
-    /// If `true` the simulation has exited. This might be because it's finished, or because an error occurred. This should be written by the simulation only.
-    pub simulation_exited: bool,
-
-    // Completion measurement
-    pub steps_complete: u32,
-    pub steps_total: u32,
-
-    // Simulation statistics
-    pub tick_time_history: Vec<f64>,
-    pub entity_count_history: Vec<f64>,
-    pub people_count_history: Vec<f64>,
-    pub place_count_history: Vec<f64>,
+/// What the simulation thread has been told to do, built up from [SimulationCommand]s.
+#[derive(Default)]
+struct ThreadControl {
+    stop: bool,
+    paused: bool,
+    steps_to_advance: u32,
+    ticks_per_second: Option<f32>,
+    snapshot_requested: bool,
+    statistics: StatisticsConfig,
 }
 
-impl Default for SimulationBoundary {
-    fn default() -> Self {
-        Self {
-            stop_next_tick: false,
-            simulation_exited: false,
-
-            steps_complete: 0,
-            steps_total: 0,
+impl ThreadControl {
+    /// Returns `true` if nothing should happen until another command arrives.
+    fn waiting(&self) -> bool {
+        self.paused && self.steps_to_advance == 0 && !self.stop && !self.snapshot_requested
+    }
 
-            tick_time_history: Vec::with_capacity(RECORD_LENGTH),
-            entity_count_history: Vec::with_capacity(RECORD_LENGTH),
-            people_count_history: Vec::with_capacity(RECORD_LENGTH),
-            place_count_history: Vec::with_capacity(RECORD_LENGTH),
+    /// Applies a command, returning anything the UI should be told about it.
+    fn apply(&mut self, command: SimulationCommand) -> Option<Telemetry> {
+        match command {
+            SimulationCommand::Stop => self.stop = true,
+            SimulationCommand::Pause => {
+                self.paused = true;
+                return Some(Telemetry::Paused(true));
+            },
+            SimulationCommand::Resume => {
+                self.paused = false;
+                self.steps_to_advance = 0;
+                return Some(Telemetry::Paused(false));
+            },
+            SimulationCommand::Step(steps) => self.steps_to_advance += steps,
+            SimulationCommand::SetSpeedLimit(ticks_per_second) => self.ticks_per_second = ticks_per_second,
+            SimulationCommand::RequestSnapshot => self.snapshot_requested = true,
+            SimulationCommand::ConfigureStatistics(statistics) => self.statistics = statistics,
         }
+        None
     }
 }
 
 #[derive(Resource)]
 pub struct SimulationComplete;
 
+/// Adds the systems for the world's direction, if they haven't been added already.
+/// This locks in the config, so it should be called right before the simulation is first executed, after [validate_world] finds no errors.
+pub fn systems_check(
+    simulation: &mut SimulationData,
+) {
+    // Get direction and timestep
+    let mut cfg = simulation.app.world.resource_mut::<SimulationConfig>();
+    if cfg.locked_in { return; } // Preset is already set
+    let (direction, seed) = (cfg.direction.clone(), cfg.seed);
+    cfg.locked_in = true;
+    drop(cfg);
+
+    // Randomness is derived from the seed, which can't change from here on
+    simulation.app.insert_resource(SimulationRng::new(seed));
+
+    // Apply systems to app
+    match direction {
+        HistoryDirection::Forwards => add_forward_presets(&mut simulation.app),
+        HistoryDirection::Backwards => add_backward_presets(&mut simulation.app),
+    }
+}
+
 /// Checks over everything in the world and ensures it's all working well.
-pub fn validate_world(_world: &mut World) {
+/// Returns every problem found, errors first.
+pub fn validate_world(world: &mut World) -> Vec<Diagnostic> {
+    let mut found = vec![];
+
+    diagnostics::check_species(world, &mut found);
+    diagnostics::check_afflictions(world, &mut found);
+    diagnostics::check_hierarchy(world, &mut found);
+    diagnostics::check_kinship(world, &mut found);
+    diagnostics::check_calendar(world, &mut found);
+    diagnostics::check_seasons(world, &mut found);
+    diagnostics::check_run_length(world, &mut found);
+    diagnostics::check_stop_conditions(world, &mut found);
+
+    found.sort_by_key(|diagnostic| (diagnostic.severity, diagnostic.entity));
+    found
+}
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Res;
+    use crate::world::{branch::DEFAULT_BRANCH_NAME, defs::SimulationConfig, save::{WorldFile, WORLD_FILE_VERSION}, stop::StopCondition, testing::{sample_world, to_text}};
+    use std::{thread, time::{Duration, Instant}};
+    use crate::world::protocol::SimulationBoundary;
+    use super::{MAX_CHECKPOINTS, Recovered, Simulation, SimulationError, systems_check};
+
+    /// Runs the simulation until it finishes by itself.
+    fn run_to_end(mut simulation: Simulation) -> Simulation {
+        systems_check(simulation.current_or_err().unwrap());
+        let (simulation, result) = simulation.try_execute();
+        result.unwrap();
+        let (simulation, result) = simulation.join();
+        result.unwrap();
+        simulation
+    }
+
+    fn fail_at_150(config: Res<SimulationConfig>) {
+        if config.increments_completed == 150 { panic!("failed on purpose"); }
+    }
+
+    /// Runs the simulation with a system that fails on the 151st tick.
+    fn run_and_fail(mut simulation: Simulation) -> (Simulation, Recovered) {
+        let data = simulation.current_or_err().unwrap();
+        systems_check(data);
+        data.app.add_system(fail_at_150);
+
+        let (simulation, result) = simulation.try_execute();
+        result.unwrap();
+        let (simulation, result) = simulation.join();
+        let failure = result.unwrap_err();
+        assert!(matches!(failure.error, SimulationError::TickPanicked { tick: 150, .. }));
+        (simulation, failure.recovered)
+    }
+
+    #[test]
+    fn failed_run_recovers_last_good_state() {
+        let (mut simulation, recovered) = run_and_fail(sample_world());
+        assert_eq!(recovered, Recovered::LastGoodState(150));
+        assert_eq!(simulation.world().unwrap().resource::<SimulationConfig>().increments_completed, 150);
+
+        simulation.restore_pre_run().unwrap();
+        assert_eq!(simulation.world().unwrap().resource::<SimulationConfig>().increments_completed, 0);
+    }
+
+    #[test]
+    fn last_good_state_matches_the_run() {
+        // Without checkpoints, it's rebuilt from the start of the run
+        let mut failing = sample_world();
+        failing.world().unwrap().resource_mut::<SimulationConfig>().checkpoint_interval = 0;
+        let text = to_text(failing.world().unwrap());
+        let (mut failing, recovered) = run_and_fail(failing);
+        assert_eq!(recovered, Recovered::LastGoodState(150));
+
+        // The same world run for exactly that long, without failing
+        let mut expected = Simulation::new(ron::from_str::<WorldFile>(&text).unwrap().restore().unwrap());
+        expected.world().unwrap().resource_mut::<SimulationConfig>().increments_for_completion = 150;
+        let mut expected = run_to_end(expected);
+        expected.world().unwrap().resource_mut::<SimulationConfig>().increments_for_completion = 600;
+
+        assert_eq!(to_text(failing.world().unwrap()), to_text(expected.world().unwrap()));
+    }
+
+    /// Waits for the simulation thread to report something, failing the test if it takes too long.
+    fn wait_for(simulation: &mut Simulation, condition: impl Fn(&SimulationBoundary) -> bool) {
+        let started = Instant::now();
+        while !simulation.status().is_some_and(&condition) {
+            assert!(started.elapsed() < Duration::from_secs(10), "the simulation thread didn't respond");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn paused_simulation_steps_and_resumes() {
+        let mut simulation = sample_world();
+        systems_check(simulation.current_or_err().unwrap());
+        let (mut simulation, result) = simulation.try_execute();
+        result.unwrap();
+
+        simulation.pause().unwrap();
+        wait_for(&mut simulation, |boundary| boundary.paused);
+        let before = simulation.status().unwrap().steps_complete;
+
+        // Exactly as many ticks as were asked for, and no more
+        simulation.advance(5).unwrap();
+        wait_for(&mut simulation, |boundary| boundary.steps_complete >= before + 5);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(simulation.status().unwrap().steps_complete, before + 5);
+
+        simulation.request_snapshot().unwrap();
+        wait_for(&mut simulation, |boundary| boundary.snapshot.is_some());
+        assert_eq!(simulation.status().unwrap().snapshot.as_ref().unwrap().tick, before + 5);
+
+        simulation.resume().unwrap();
+        let (mut simulation, result) = simulation.join();
+        result.unwrap();
+        let config = simulation.world().unwrap().resource::<SimulationConfig>();
+        assert_eq!(config.increments_completed, config.increments_for_completion);
+    }
+
+    #[test]
+    fn finished_world_does_not_start() {
+        let mut simulation = sample_world();
+        let mut config = simulation.world().unwrap().resource_mut::<SimulationConfig>();
+        config.increments_completed = 700;
+        config.increments_for_completion = 600;
+
+        let (mut simulation, result) = simulation.try_execute();
+        assert!(matches!(result, Err(SimulationError::AlreadyFinished)));
+        assert_eq!(simulation.world().unwrap().resource::<SimulationConfig>().increments_completed, 700);
+    }
+
+    #[test]
+    fn resuming_keeps_pre_run_snapshot() {
+        let mut simulation = sample_world();
+        simulation.world().unwrap().resource_mut::<SimulationConfig>().increments_for_completion = 50;
+        let mut simulation = run_to_end(simulation);
 
-}
\ No newline at end of file
+        // Carry on from where the first run ended
+        simulation.world().unwrap().resource_mut::<SimulationConfig>().increments_for_completion = 80;
+        let mut simulation = run_to_end(simulation);
+        assert_eq!(simulation.world().unwrap().resource::<SimulationConfig>().increments_completed, 80);
+
+        simulation.restore_pre_run().unwrap();
+        assert_eq!(simulation.world().unwrap().resource::<SimulationConfig>().increments_completed, 0);
+    }
+
+    #[test]
+    fn old_checkpoints_are_dropped() {
+        let mut simulation = sample_world();
+        let mut config = simulation.world().unwrap().resource_mut::<SimulationConfig>();
+        config.checkpoint_interval = 1;
+        config.increments_for_completion = 50;
+        let simulation = run_to_end(simulation);
+
+        let ticks: Vec<u32> = simulation.checkpoints().iter().map(|checkpoint| checkpoint.tick).collect();
+        assert_eq!(ticks, (31..=50).collect::<Vec<u32>>());
+        assert_eq!(ticks.len(), MAX_CHECKPOINTS);
+    }
+
+    #[test]
+    fn failed_switch_keeps_branch() {
+        let mut simulation = sample_world();
+        simulation.world().unwrap().resource_mut::<SimulationConfig>().increments_for_completion = 20;
+        let mut simulation = run_to_end(simulation);
+        simulation.fork_branch("other").unwrap();
+        simulation.stopped_by = Some(StopCondition::NoLivingPeople);
+
+        // A branch that can't be restored stays where it was
+        simulation.branches[0].world.version = u32::MAX;
+        assert!(simulation.switch_branch(DEFAULT_BRANCH_NAME).is_err());
+        assert_eq!(simulation.branch_names(), vec![DEFAULT_BRANCH_NAME.to_string(), "other".to_string()]);
+
+        simulation.branches[0].world.version = WORLD_FILE_VERSION;
+        simulation.switch_branch(DEFAULT_BRANCH_NAME).unwrap();
+        assert_eq!(simulation.world().unwrap().resource::<SimulationConfig>().increments_completed, 20);
+        assert_eq!(simulation.stopped_by(), None);
+
+        simulation.switch_branch("other").unwrap();
+        assert_eq!(simulation.stopped_by(), Some(&StopCondition::NoLivingPeople));
+    }
+
+    #[test]
+    fn same_seed_same_world() {
+        let mut outcomes = vec![];
+        for _ in 0..2 {
+            let mut simulation = sample_world();
+            simulation.world().unwrap().resource_mut::<SimulationConfig>().seed = 42;
+            let mut simulation = run_to_end(simulation);
+            outcomes.push(to_text(simulation.world().unwrap()));
+        }
+
+        assert!(outcomes[0].contains("increments_completed: 600"));
+        assert_eq!(outcomes[0], outcomes[1]);
+    }
+}
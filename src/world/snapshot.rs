@@ -0,0 +1,107 @@
+//! Read-only copies of the world, for looking at while the simulation is executing.
+
+use bevy::{ecs::prelude::*, prelude::Parent};
+use super::{
+    common::Name,
+    defs::{SimulationConfig, species::AssociatedSpecies},
+    living::{Living, health::CachedHealth, afflictions::Afflicted},
+    person::Person,
+    place::{Region, Settlement},
+    time::Age,
+};
+
+/// A copy of the people and places in the world at one point in time.
+#[derive(Debug, Clone)]
+pub struct WorldSnapshot {
+    /// How many increments had been completed when this was taken.
+    pub tick: u32,
+    /// How much in-world time had passed when this was taken.
+    pub date: Age,
+    pub people: Vec<PersonSnapshot>,
+    pub places: Vec<PlaceSnapshot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PersonSnapshot {
+    pub entity: Entity,
+    pub name: String,
+    pub age: Age,
+    pub living: Living,
+    /// `None` if health hasn't been worked out yet.
+    pub health: Option<f32>,
+    pub species: Option<String>,
+    /// The name of the place this person is in.
+    pub place: Option<String>,
+    /// The names of this person's afflictions.
+    pub afflictions: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaceSnapshot {
+    pub entity: Entity,
+    pub name: String,
+    /// `true` for settlements, `false` for regions.
+    pub settlement: bool,
+    /// The population of a settlement.
+    pub population: Option<u32>,
+    /// The name of the place this is inside of.
+    pub parent: Option<String>,
+    /// How many living people are directly inside this place.
+    pub residents: u32,
+}
+
+impl WorldSnapshot {
+    /// Copies the people and places out of the world.
+    pub fn capture(world: &mut World) -> Self {
+        let config = world.resource::<SimulationConfig>();
+        let (tick, date) = (config.increments_completed, config.now());
+
+        let name_of = |world: &World, entity: Entity| world.get::<Name>(entity).map(|name| name.0.clone());
+
+        let mut people = vec![];
+        let mut query = world.query_filtered::<(Entity, &Name, &Age, &Living, Option<&CachedHealth>), With<Person>>();
+        for (entity, name, age, living, health) in query.iter(world) {
+            let afflictions = world.get::<Afflicted>(entity)
+                .map(|afflicted| afflicted.iter().filter_map(|(affliction, _)| name_of(world, *affliction)).collect())
+                .unwrap_or_default();
+
+            people.push(PersonSnapshot {
+                entity,
+                name: name.0.clone(),
+                age: *age,
+                living: living.clone(),
+                health: health.map(|health| health.read()).filter(|health| health.is_finite()),
+                species: world.get::<AssociatedSpecies>(entity).and_then(|species| name_of(world, species.0)),
+                place: world.get::<Parent>(entity).and_then(|parent| name_of(world, parent.get())),
+                afflictions,
+            });
+        }
+
+        let mut places = vec![];
+        let mut query = world.query_filtered::<(Entity, &Name, Option<&Settlement>), Or<(With<Region>, With<Settlement>)>>();
+        for (entity, name, settlement) in query.iter(world) {
+            places.push(PlaceSnapshot {
+                entity,
+                name: name.0.clone(),
+                settlement: settlement.is_some(),
+                population: settlement.map(|settlement| settlement.population),
+                parent: world.get::<Parent>(entity).and_then(|parent| name_of(world, parent.get())),
+                residents: 0,
+            });
+        }
+
+        // Count living people in each place
+        for person in people.iter() {
+            if person.living == Living::Dead { continue; }
+            let Some(parent) = world.get::<Parent>(person.entity) else { continue };
+            if let Some(place) = places.iter_mut().find(|place| place.entity == parent.get()) {
+                place.residents += 1;
+            }
+        }
+
+        people.sort_by_key(|person| person.entity);
+        places.sort_by_key(|place| place.entity);
+
+        Self { tick, date, people, places }
+    }
+}
@@ -0,0 +1,337 @@
+//! Statistics that can be collected while the simulation is executing, and exported afterwards.
+
+use std::{collections::{BTreeMap, BTreeSet}, fs, path::Path};
+use bevy::ecs::prelude::*;
+use super::{
+    calendar::Calendar,
+    common::Name,
+    defs::species::{AssociatedSpecies, Species},
+    living::{Living, health::CachedHealth, afflictions::{Afflicted, Affliction}},
+    person::Person,
+    place::{Region, Settlement, living_residents},
+    time::Age,
+};
+
+/// A named measurement of the world, made up of one or more series.
+pub struct Probe {
+    pub name: &'static str,
+    /// What's being measured, intended for people to read.
+    pub description: &'static str,
+    /// Turns a value into text for people to read.
+    pub format: fn(f64) -> String,
+    /// Measures the world, given how long the last tick took in seconds. Returns a value for each series.
+    sample: fn(&mut World, f64) -> Vec<(String, f64)>,
+}
+
+/// Every statistic that can be collected.
+pub const PROBES: &[Probe] = &[
+    Probe { name: "Tick time", description: "How long each step took to run", format: format_seconds, sample: sample_tick_time },
+    Probe { name: "Entities", description: "Everything in the world, including definitions", format: format_count, sample: sample_entities },
+    Probe { name: "People", description: "People, whether alive or dead", format: format_count, sample: sample_people },
+    Probe { name: "Places", description: "Regions and settlements", format: format_count, sample: sample_places },
+    Probe { name: "Living and dead", description: "Living things that are alive, and those that have died", format: format_count, sample: sample_living },
+    Probe { name: "Average health", description: "The average health of living people", format: format_decimal, sample: sample_health },
+    Probe { name: "Population by species", description: "Living people of each species", format: format_count, sample: sample_species },
+    Probe { name: "Active afflictions", description: "Living people with each affliction", format: format_count, sample: sample_afflictions },
+    Probe { name: "Settlement population", description: "Living people in each settlement", format: format_count, sample: sample_settlements },
+];
+
+/// Which statistics to collect, and how often.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatisticsConfig {
+    /// The names of the probes to sample.
+    pub enabled: BTreeSet<&'static str>,
+    /// How many ticks between samples.
+    pub interval: u32,
+}
+
+impl Default for StatisticsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: BTreeSet::from(["Tick time", "Entities", "People", "Places"]),
+            interval: 1,
+        }
+    }
+}
+
+impl StatisticsConfig {
+    /// Returns `true` if a sample is due after `tick` ticks have been completed.
+    pub fn due(&self, tick: u32) -> bool {
+        self.interval != 0 && tick.is_multiple_of(self.interval)
+    }
+
+    /// Samples every enabled probe.
+    pub fn sample(&self, world: &mut World, tick_time: f64) -> Vec<Sample> {
+        let mut samples = vec![];
+        for probe in PROBES.iter().filter(|probe| self.enabled.contains(probe.name)) {
+            for (series, value) in (probe.sample)(world, tick_time) {
+                samples.push(Sample { probe: probe.name, series, value });
+            }
+        }
+        samples
+    }
+}
+
+/// One value from one series of a probe.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub probe: &'static str,
+    pub series: String,
+    pub value: f64,
+}
+
+/// Values of one series, by the number of ticks completed when they were sampled.
+pub type Series = BTreeMap<u32, f64>;
+
+/// Every sample collected, kept so it can be looked at or exported after the run.
+#[derive(Debug, Clone, Default)]
+pub struct Statistics {
+    /// The in-world date of each tick that was sampled.
+    dates: BTreeMap<u32, Age>,
+    /// Series for each probe, by name.
+    probes: BTreeMap<&'static str, BTreeMap<String, Series>>,
+}
+
+impl Statistics {
+    pub fn record(&mut self, tick: u32, date: Age, samples: Vec<Sample>) {
+        self.dates.insert(tick, date);
+        for sample in samples {
+            self.probes.entry(sample.probe).or_default()
+                .entry(sample.series).or_default()
+                .insert(tick, sample.value);
+        }
+    }
+
+    /// Adds everything from `other`, replacing samples from the same ticks.
+    pub fn merge(&mut self, other: Statistics) {
+        self.dates.extend(other.dates);
+        for (probe, series) in other.probes {
+            let existing = self.probes.entry(probe).or_default();
+            for (name, values) in series {
+                existing.entry(name).or_default().extend(values);
+            }
+        }
+    }
+
+    /// Forgets samples taken after `tick` ticks were completed, since they're from a history that's being rewritten.
+    pub fn retain_until(&mut self, tick: u32) {
+        self.dates.retain(|sampled, _| *sampled <= tick);
+        for series in self.probes.values_mut().flat_map(|series| series.values_mut()) {
+            series.retain(|sampled, _| *sampled <= tick);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dates.is_empty()
+    }
+
+    /// Returns every series collected for the probe called `name`.
+    pub fn series(&self, name: &str) -> Option<&BTreeMap<String, Series>> {
+        self.probes.get(name)
+    }
+
+    /// Returns the points of a series as `[years, value]`, for plotting against in-world time.
+    /// Years are as long as they are in `calendar`.
+    pub fn points(&self, series: &Series, calendar: &Calendar) -> Vec<[f64; 2]> {
+        let year_length = calendar.year_length().max(1) as f64;
+        series.iter()
+            .map(|(tick, value)| [self.dates.get(tick).map_or(0, |date| date.days_passed()) as f64 / year_length, *value])
+            .collect()
+    }
+
+    /// Writes every series as comma separated values, with a row for each sampled tick and a column for each series.
+    pub fn to_csv(&self, calendar: &Calendar) -> String {
+        // Columns in the same order as the probes are listed, with their series in alphabetical order
+        let columns: Vec<(String, &Series)> = PROBES.iter()
+            .filter_map(|probe| self.probes.get(probe.name).map(|series| (probe.name, series)))
+            .flat_map(|(probe, series)| series.iter().map(move |(name, values)| {
+                let heading = if name == probe { name.clone() } else { format!("{}: {}", probe, name) };
+                (heading, values)
+            }))
+            .collect();
+
+        let mut csv = String::from("Tick,Day,Date");
+        for (heading, _) in columns.iter() {
+            csv.push(',');
+            csv.push_str(&csv_field(heading));
+        }
+        csv.push('\n');
+
+        for (tick, date) in self.dates.iter() {
+            csv.push_str(&format!("{},{},{}", tick, date.days_passed(), csv_field(&calendar.format_after(*date))));
+            for (_, values) in columns.iter() {
+                csv.push(',');
+                // Series that weren't being collected yet, or had nothing in them, are left empty
+                if let Some(value) = values.get(tick) { csv.push_str(&value.to_string()); }
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    pub fn write_csv(&self, path: impl AsRef<Path>, calendar: &Calendar) -> std::io::Result<()> {
+        fs::write(path, self.to_csv(calendar))
+    }
+}
+
+/// Quotes a value if it would otherwise break up the row.
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+fn format_count(value: f64) -> String {
+    format!("{}", value.floor())
+}
+
+fn format_decimal(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+fn format_seconds(value: f64) -> String {
+    match value < 1.0 {
+        true => format!("{:.3} milliseconds", value * 1000.0),
+        false => format!("{:.4} seconds", value),
+    }
+}
+
+/// Returns a single series named after the probe.
+fn single(name: &str, value: f64) -> Vec<(String, f64)> {
+    vec![(name.to_string(), value)]
+}
+
+fn sample_tick_time(_world: &mut World, tick_time: f64) -> Vec<(String, f64)> {
+    single("Tick time", tick_time)
+}
+
+fn sample_entities(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    single("Entities", world.query::<Entity>().iter(world).len() as f64)
+}
+
+fn sample_people(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    single("People", world.query_filtered::<Entity, With<Person>>().iter(world).len() as f64)
+}
+
+fn sample_places(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    single("Places", world.query_filtered::<Entity, Or<(With<Region>, With<Settlement>)>>().iter(world).len() as f64)
+}
+
+fn sample_living(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    let (mut alive, mut dead) = (0, 0);
+    for living in world.query::<&Living>().iter(world) {
+        match living {
+            Living::Alive => alive += 1,
+            Living::Dead => dead += 1,
+        }
+    }
+    vec![("Alive".to_string(), alive as f64), ("Dead".to_string(), dead as f64)]
+}
+
+fn sample_health(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    let health: Vec<f32> = world.query_filtered::<(&Living, &CachedHealth), With<Person>>().iter(world)
+        .filter(|(living, _)| **living == Living::Alive)
+        .map(|(_, health)| health.read())
+        .filter(|health| health.is_finite())
+        .collect();
+
+    match health.is_empty() {
+        true => vec![],
+        false => single("Average health", health.iter().sum::<f32>() as f64 / health.len() as f64),
+    }
+}
+
+/// Counts living people for each name that `key` gives them.
+fn count_living_people_by(world: &mut World, key: impl Fn(&World, Entity) -> Vec<String>) -> Vec<(String, f64)> {
+    let people: Vec<Entity> = world.query_filtered::<(Entity, &Living), With<Person>>().iter(world)
+        .filter(|(_, living)| **living == Living::Alive)
+        .map(|(entity, _)| entity)
+        .collect();
+
+    let mut counts: BTreeMap<String, f64> = BTreeMap::new();
+    for person in people {
+        for name in key(world, person) {
+            *counts.entry(name).or_default() += 1.0;
+        }
+    }
+    counts.into_iter().collect()
+}
+
+/// Returns series names for every entity with a `T` component.
+/// Entities that share a name are told apart by their ID, so their series aren't merged together.
+fn series_names<T: Component>(world: &mut World) -> BTreeMap<Entity, String> {
+    let entities: Vec<(Entity, Option<String>)> = world.query_filtered::<(Entity, Option<&Name>), With<T>>().iter(world)
+        .map(|(entity, name)| (entity, name.map(|name| name.0.clone())))
+        .collect();
+
+    let mut uses: BTreeMap<&str, u32> = BTreeMap::new();
+    for name in entities.iter().filter_map(|(_, name)| name.as_deref()) { *uses.entry(name).or_default() += 1; }
+
+    entities.iter().map(|(entity, name)| {
+        let series = match name {
+            Some(name) if uses[name.as_str()] > 1 => format!("{} ({:?})", name, entity),
+            Some(name) => name.clone(),
+            None => format!("{:?}", entity),
+        };
+        (*entity, series)
+    }).collect()
+}
+
+fn sample_species(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    let names = series_names::<Species>(world);
+    count_living_people_by(world, |world, person| {
+        world.get::<AssociatedSpecies>(person).and_then(|species| names.get(&species.0)).cloned().into_iter().collect()
+    })
+}
+
+fn sample_afflictions(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    let names = series_names::<Affliction>(world);
+    count_living_people_by(world, |world, person| {
+        world.get::<Afflicted>(person)
+            .map(|afflicted| afflicted.iter().filter_map(|(affliction, _)| names.get(affliction)).cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
+fn sample_settlements(world: &mut World, _tick_time: f64) -> Vec<(String, f64)> {
+    let names = series_names::<Settlement>(world);
+    let residents = living_residents(world);
+    names.into_iter()
+        .map(|(entity, name)| (name, residents.get(&entity).copied().unwrap_or(0) as f64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use crate::world::{calendar::{Calendar, Month}, common::Name, living::Living, place::{Settlement, SettlementBundle}, testing::{sample_world, named}, time::Age};
+    use super::{Sample, Statistics, sample_settlements};
+
+    #[test]
+    fn points_use_the_calendar_year() {
+        let mut statistics = Statistics::default();
+        statistics.record(10, Age::from_days(300), vec![Sample { probe: "People", series: "People".into(), value: 4.0 }]);
+
+        let calendar = Calendar { months: vec![Month { name: "Only".into(), days: 100 }], ..Default::default() };
+        let series = &statistics.series("People").unwrap()["People"];
+        assert_eq!(statistics.points(series, &calendar), vec![[3.0, 4.0]]);
+    }
+
+    #[test]
+    fn settlements_with_the_same_name_are_kept_apart() {
+        let mut simulation = sample_world();
+        let world = simulation.world().unwrap();
+        let other = world.spawn(SettlementBundle { name: Name("Town".into()), settlement: Settlement { population: 30 } }).id();
+        let (p1, p2, p3) = (named(world, "P1"), named(world, "P2"), named(world, "P3"));
+        world.entity_mut(other).push_children(&[p1, p2, p3]);
+        world.entity_mut(p3).insert(Living::Dead);
+
+        // Counted from the living people who live there, not the population the town was given
+        let samples = sample_settlements(world, 0.0);
+        assert_eq!(samples.len(), 2);
+        assert!(samples.contains(&(format!("Town ({:?})", other), 2.0)));
+        assert!(samples.iter().all(|(name, _)| name.starts_with("Town (")));
+    }
+}
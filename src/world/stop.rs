@@ -0,0 +1,150 @@
+//! Conditions that end a run early, so history can be generated up to a particular moment.
+
+use std::fmt::Display;
+use bevy::ecs::{prelude::*, system::Resource};
+use serde::{Serialize, Deserialize};
+use super::{common::Name, faction::Faction, living::Living, person::Person, place::{Settlement, living_residents}};
+
+/// Something that ends a run when it happens.
+/// People, settlements, and factions are found by name, so conditions still work after the world is saved and loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopCondition {
+    /// Everyone with this name has died.
+    PersonDies(String),
+    /// The number of living people in the settlement with this name drops below this amount.
+    PopulationBelow {
+        settlement: String,
+        population: u32,
+    },
+    /// There are no factions with this name left.
+    FactionGone(String),
+    /// Every person has died.
+    NoLivingPeople,
+}
+
+impl StopCondition {
+    /// Returns `true` if the condition is true of the world right now.
+    pub fn met(&self, world: &mut World) -> bool {
+        match self {
+            StopCondition::PersonDies(name) => {
+                let mut query = world.query_filtered::<(&Name, &Living), With<Person>>();
+                let mut named = query.iter(world).filter(|(person, _)| person.0 == *name).peekable();
+                named.peek().is_some() && named.all(|(_, living)| *living == Living::Dead)
+            },
+            StopCondition::PopulationBelow { settlement, population } => {
+                let residents = living_residents(world);
+                let mut query = world.query_filtered::<(Entity, &Name), With<Settlement>>();
+                query.iter(world).any(|(entity, name)| name.0 == *settlement && residents.get(&entity).copied().unwrap_or(0) < *population)
+            },
+            StopCondition::FactionGone(name) => {
+                let mut query = world.query_filtered::<&Name, With<Faction>>();
+                !query.iter(world).any(|faction| faction.0 == *name)
+            },
+            StopCondition::NoLivingPeople => {
+                let mut query = world.query_filtered::<&Living, With<Person>>();
+                !query.iter(world).any(|living| *living == Living::Alive)
+            },
+        }
+    }
+
+    /// Returns the name of whatever the condition is about, if it's about something in particular.
+    pub fn subject(&self) -> Option<&str> {
+        match self {
+            StopCondition::PersonDies(name) | StopCondition::FactionGone(name) => Some(name),
+            StopCondition::PopulationBelow { settlement, .. } => Some(settlement),
+            StopCondition::NoLivingPeople => None,
+        }
+    }
+}
+
+impl Display for StopCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopCondition::PersonDies(name) => write!(f, "{} dies", name),
+            StopCondition::PopulationBelow { settlement, population } => write!(f, "the population of {} drops below {}", settlement, population),
+            StopCondition::FactionGone(name) => write!(f, "{} ceases to exist", name),
+            StopCondition::NoLivingPeople => write!(f, "no living people remain"),
+        }
+    }
+}
+
+/// The conditions that end a run early. Runs stop as soon as any one of them is met.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StopConditions(pub Vec<StopCondition>);
+
+impl StopConditions {
+    /// Returns the conditions that aren't already met, since they can't be what stops the run.
+    pub fn pending(&self, world: &mut World) -> Vec<StopCondition> {
+        self.0.iter().filter(|condition| !condition.met(world)).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use crate::world::{common::Name, faction::Faction, living::Living, testing::{sample_world, named}};
+    use super::{StopCondition, StopConditions};
+
+    #[test]
+    fn person_dies() {
+        let mut simulation = sample_world();
+        let world = simulation.world().unwrap();
+        let condition = StopCondition::PersonDies("P3".to_string());
+        assert!(!condition.met(world));
+        assert!(!StopCondition::PersonDies("Nobody".to_string()).met(world));
+
+        let p3 = named(world, "P3");
+        world.entity_mut(p3).insert(Living::Dead);
+        assert!(condition.met(world));
+    }
+
+    #[test]
+    fn population_below_counts_living_residents() {
+        let mut simulation = sample_world();
+        let world = simulation.world().unwrap();
+        let (town, p1, p2) = (named(world, "Town"), named(world, "P1"), named(world, "P2"));
+        world.entity_mut(town).push_children(&[p1, p2]);
+
+        // The town was authored with a hundred people, but only two live there
+        let condition = StopCondition::PopulationBelow { settlement: "Town".to_string(), population: 2 };
+        assert!(!condition.met(world));
+
+        world.entity_mut(p1).insert(Living::Dead);
+        assert!(condition.met(world));
+    }
+
+    #[test]
+    fn faction_gone() {
+        let mut simulation = sample_world();
+        let world = simulation.world().unwrap();
+        let faction = world.spawn((Name("Guild".to_string()), Faction::default())).id();
+        let condition = StopCondition::FactionGone("Guild".to_string());
+        assert!(!condition.met(world));
+
+        world.despawn(faction);
+        assert!(condition.met(world));
+    }
+
+    #[test]
+    fn no_living_people() {
+        let mut simulation = sample_world();
+        let world = simulation.world().unwrap();
+        assert!(!StopCondition::NoLivingPeople.met(world));
+
+        let mut people = world.query::<&mut Living>();
+        for mut living in people.iter_mut(world) { *living = Living::Dead; }
+        assert!(StopCondition::NoLivingPeople.met(world));
+    }
+
+    #[test]
+    fn conditions_already_met_are_left_out() {
+        let mut simulation = sample_world();
+        let world = simulation.world().unwrap();
+        let conditions = StopConditions(vec![
+            StopCondition::PersonDies("P0".to_string()),
+            StopCondition::PopulationBelow { settlement: "Town".to_string(), population: 1 },
+            StopCondition::FactionGone("Guild".to_string()),
+        ]);
+        assert_eq!(conditions.pending(world), vec![StopCondition::PersonDies("P0".to_string())]);
+    }
+}
@@ -0,0 +1,73 @@
+//! Small worlds shared by the tests.
+
+use std::collections::BTreeMap;
+use bevy::prelude::*;
+use super::{
+    common::Name,
+    defs::species::{AssociatedSpecies, Species, SpeciesBundle},
+    living::{Living, afflictions::{Afflicted, Affliction, AfflictionBundle, SeverityVariableValue}, health::CachedHealth},
+    person::{Person, PersonBundle, Personality},
+    place::{RegionBundle, Settlement, SettlementBundle},
+    save::WorldFile,
+    sim::Simulation,
+    time::Age,
+};
+
+/// A region with one settlement in it, and six unrelated people aged 20 to 45, the youngest of whom is sick.
+pub fn sample_world() -> Simulation {
+    let mut sim = Simulation::default();
+    let world = sim.world().unwrap();
+
+    let species = world.spawn(SpeciesBundle {
+        name: Name("Human".into()),
+        species: Species {
+            humanoid: true,
+            maturity_age: Age::from_years(18),
+            max_age: Age::from_years(80),
+            resilience: 100.0,
+            immunity: 1.0,
+            fertility: 1.0,
+            fertile_until: Age::from_years(50),
+            aging: Default::default(),
+        },
+    }).id();
+
+    let plague = world.spawn(AfflictionBundle {
+        name: Name("Plague".into()),
+        affliction: Affliction {
+            flat: SeverityVariableValue::Scaling(-1.0),
+            coefficient: SeverityVariableValue::NoAdjustment,
+            progression_speed: SeverityVariableValue::Static(0.5),
+        },
+    }).id();
+
+    for i in 0..6 {
+        let mut person = world.spawn(PersonBundle {
+            person: Person,
+            personality: Personality::default(),
+            name: Name(format!("P{i}")),
+            age: Age::from_years(20 + i * 5),
+            state: Living::Alive,
+            health: CachedHealth::new(),
+        });
+        person.insert(AssociatedSpecies(species));
+        if i == 0 { person.insert(Afflicted(BTreeMap::from([(plague, 1.0)]))); }
+    }
+
+    let region = world.spawn(RegionBundle::default()).id();
+    let town = world.spawn(SettlementBundle { name: Name("Town".into()), settlement: Settlement { population: 100 } }).id();
+    world.entity_mut(region).push_children(&[town]);
+
+    sim
+}
+
+/// Returns the world as it would be written to a world file.
+pub fn to_text(world: &mut World) -> String {
+    ron::ser::to_string_pretty(&WorldFile::capture(world).unwrap(), ron::ser::PrettyConfig::default()).unwrap()
+}
+
+/// Returns the entity with this name.
+pub fn named(world: &mut World, name: &str) -> Entity {
+    let mut query = world.query::<(Entity, &Name)>();
+    query.iter(world).find(|(_, found)| found.0 == name).map(|(entity, _)| entity).unwrap()
+}
@@ -1,11 +1,12 @@
 //! In-simulation time.
 
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr, time::Duration};
 use bevy::prelude::Component;
 use eframe::emath::Numeric;
+use serde::{Serialize, Deserialize};
 
 /// Tracks time in days.
-#[derive(Component, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Component, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Age(u32);
 
 impl Age {
@@ -27,7 +28,7 @@ impl Age {
     }
 
     pub const fn from_years(years: u32) -> Self {
-        Age(years * 360)
+        Age(years.saturating_mul(360))
     }
 
     /// Returns how many months have passed.
@@ -36,7 +37,7 @@ impl Age {
     }
 
     pub const fn from_months(months: u32) -> Self {
-        Age(months * 30)
+        Age(months.saturating_mul(30))
     }
 
     /// Returns how many days have passed.
@@ -49,15 +50,15 @@ impl Age {
     }
 
     pub fn add_years(&mut self, amount: u32) {
-        self.0 += amount * 360;
+        self.0 = self.0.saturating_add(amount.saturating_mul(360));
     }
 
     pub fn add_months(&mut self, amount: u32) {
-        self.0 += amount * 30;
+        self.0 = self.0.saturating_add(amount.saturating_mul(30));
     }
 
     pub fn add_days(&mut self, amount: u32) {
-        self.0 += amount;
+        self.0 = self.0.saturating_add(amount);
     }
 }
 
@@ -105,14 +106,55 @@ impl FromStr for Age {
 
             let val = chunk[1];
 
-            match val {
-                "years" | "year" | "y" => { time.add_years(num); },
-                "months" | "month" | "m" => { time.add_months(num); },
-                "days" | "day" | "d" => { time.add_days(num); }
+            // Lengths too long to count in days are refused, rather than quietly shortened
+            let days = match val {
+                "years" | "year" | "y" => num.checked_mul(360),
+                "months" | "month" | "m" => num.checked_mul(30),
+                "days" | "day" | "d" => Some(num),
                 _ => { return Err(()); }
-            }
+            };
+            time = days.and_then(|days| time.0.checked_add(days)).map(Age).ok_or(())?;
         }
 
         Ok(time)
     }
-}
\ No newline at end of file
+}
+
+/// Writes a real-world length of time roughly, like "3 minutes 20 seconds". Partial seconds are rounded up.
+pub fn format_duration(duration: Duration) -> String {
+    let unit = |amount: u64, name: &str| format!("{} {}{}", amount, name, if amount == 1 { "" } else { "s" });
+
+    let seconds = duration.as_secs_f64().ceil() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+
+    match (hours, minutes) {
+        (0, 0) => unit(seconds, "second"),
+        (0, minutes) => format!("{} {}", unit(minutes, "minute"), unit(seconds, "second")),
+        (hours, minutes) => format!("{} {}", unit(hours, "hour"), unit(minutes, "minute")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::Age;
+
+    #[test]
+    fn parses_lengths() {
+        assert_eq!(Age::from_str("200 years 6 months 10 days"), Ok(Age::from_days(200 * 360 + 6 * 30 + 10)));
+        assert_eq!(Age::from_str("3 y 2 d"), Ok(Age::from_days(3 * 360 + 2)));
+        assert_eq!(Age::from_str("3 weeks"), Err(()));
+        assert_eq!(Age::from_str("3"), Err(()));
+    }
+
+    #[test]
+    fn overlong_lengths_are_refused() {
+        assert_eq!(Age::from_str("99999999 years"), Err(()));
+        assert_eq!(Age::from_str("4294967295 days 1 day"), Err(()));
+        assert_eq!(Age::from_years(u32::MAX), Age::from_days(u32::MAX));
+
+        let mut age = Age::from_days(u32::MAX - 1);
+        age.add_months(1);
+        assert_eq!(age, Age::from_days(u32::MAX));
+    }
+}